@@ -0,0 +1,265 @@
+use puzzle_core::effect;
+use puzzle_core::gap;
+use puzzle_core::group;
+use puzzle_core::metric::MoveCount;
+use puzzle_core::notation;
+use puzzle_core::permutation::format_cycles;
+use puzzle_core::puzzle::{EdgeFacet, Face, Hex, Puzzle, Square};
+use puzzle_core::scramble;
+use puzzle_core::solver::Solver;
+use puzzle_core::state::State;
+use puzzle_core::terminal;
+use std::io::Read;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+usage: puzzle-cli [--scramble SEED] [--gap-word] [--cancel] [--effect] [--check] [--solve] [--draw] [--patterns] [MOVES...]
+       puzzle-cli --group
+       puzzle-cli --gap
+
+Applies a move sequence to a solved puzzle and prints the result.
+Moves are read from the arguments, or from stdin when there are none,
+e.g. `puzzle-cli H J' K2 L`.
+
+  --scramble  start from a uniformly random state, the same one for the same seed
+  --gap-word  read the moves as a GAP word in the generators of --gap, e.g. `H*J^-1`
+  --cancel    simplify the moves, merging twists about the same axis, before applying them
+  --effect    report the net effect of the moves: cycles, turned squares, order and commutator form
+  --check     exit with status 1 unless the moves solve the puzzle
+  --solve     print a shortest sequence that solves the resulting state
+  --draw      draw the puzzle from two sides with 24-bit terminal colors
+  --patterns  overlay the color-blind patterns on the drawing
+  --group     describe the group of all reachable states instead
+  --gap       print the group as a GAP program instead
+";
+
+// columns per view
+const DRAWING_SIZE: usize = 40;
+
+fn main() -> ExitCode {
+  let mut check = false;
+  let mut solve = false;
+  let mut draw = false;
+  let mut patterns = false;
+  let mut gap_word = false;
+  let mut effect = false;
+  let mut cancel = false;
+  let mut seed = None;
+  let mut moves = Vec::new();
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--scramble" => match args.next().map(|s| s.parse::<u64>()) {
+        Some(Ok(s)) => seed = Some(s),
+        _ => {
+          eprintln!("error: --scramble needs a numeric seed\n\n{USAGE}");
+          return ExitCode::from(2);
+        }
+      },
+      "--gap-word" => gap_word = true,
+      "--effect" => effect = true,
+      "--cancel" => cancel = true,
+      "--check" => check = true,
+      "--solve" => solve = true,
+      "--draw" => draw = true,
+      "--patterns" => patterns = true,
+      "--group" => {
+        print_group();
+        return ExitCode::SUCCESS;
+      }
+      "--gap" => {
+        print!("{}", gap::export());
+        return ExitCode::SUCCESS;
+      }
+      "-h" | "--help" => {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+      }
+      _ => moves.push(arg),
+    }
+  }
+
+  let mut moves = moves.join(" ");
+  if moves.is_empty() && seed.is_none() {
+    if let Err(error) = std::io::stdin().read_to_string(&mut moves) {
+      eprintln!("error: {error}");
+      return ExitCode::FAILURE;
+    }
+  }
+
+  let scramble = seed.map(|seed| scramble::scramble(&Solver::new(), seed).twists);
+  let parsed = if gap_word {
+    gap::import(&moves)
+  } else {
+    notation::parse(&moves)
+  };
+  let mut twists = match parsed {
+    Ok(twists) => scramble
+      .iter()
+      .flatten()
+      .copied()
+      .chain(twists)
+      .collect::<Vec<_>>(),
+    Err(error) => {
+      eprintln!("error: {error}\n\n{USAGE}");
+      return ExitCode::from(2);
+    }
+  };
+  if cancel {
+    twists = notation::simplify(&twists);
+  }
+
+  let mut puzzle = Puzzle::new();
+  for twist in &twists {
+    puzzle.twist(*twist);
+  }
+
+  if let (Some(seed), Some(scramble)) = (seed, &scramble) {
+    println!("scramble:  {} (seed {seed})", notation::format(scramble));
+  }
+  println!("moves:     {}", notation::format(&twists));
+  println!(
+    "inverse:   {}",
+    notation::format(&notation::inverse(&twists))
+  );
+  let count = MoveCount::of(&twists);
+  println!("count:     {} twists, {} turns", count.twists, count.turns);
+  println!(
+    "solved:    {}",
+    if puzzle.is_solved() { "yes" } else { "no" }
+  );
+  if solve {
+    let solution = Solver::new().solve(&puzzle);
+    println!(
+      "solution:  {} ({} twists)",
+      notation::format(&solution),
+      solution.len()
+    );
+  }
+  if effect {
+    println!();
+    println!("effect:");
+    for line in effect::analyze(&twists).to_string().lines() {
+      println!("  {line}");
+    }
+  }
+  println!();
+  print_cycles(&puzzle);
+  println!();
+  print_faces(&puzzle);
+
+  if draw {
+    let mut state = State::new();
+    state.twist_buffer.extend(&twists);
+    state.finish_twists();
+    println!();
+    print!("{}", terminal::render(&state, DRAWING_SIZE, patterns));
+  }
+
+  if check && !puzzle.is_solved() {
+    ExitCode::FAILURE
+  } else {
+    ExitCode::SUCCESS
+  }
+}
+
+fn edge_name(EdgeFacet(square, hex): EdgeFacet) -> String {
+  format!("{square:?}-{hex:?}")
+}
+
+fn face_name(face: Face) -> String {
+  match face {
+    Face::Square(square) => format!("{square:?}"),
+    Face::Hex(hex) => format!("{hex:?}"),
+  }
+}
+
+/// Each cycle lists facets in the order they replace one another.
+fn print_cycles(puzzle: &Puzzle) {
+  println!("cycles:");
+  println!(
+    "  edges:     {}",
+    format_cycles(&puzzle.edge_permutation.cycles(), |k| edge_name(
+      puzzle.edges[k as usize]
+    ))
+  );
+  println!(
+    "  squares:   {}",
+    format_cycles(&puzzle.square_permutation.cycles(), |k| {
+      format!("{:?}", puzzle.squares[k as usize].0)
+    })
+  );
+  println!(
+    "  triangles: {}",
+    format_cycles(&puzzle.triangle_permutation.cycles(), |k| {
+      format!("{:?}", puzzle.triangles[k as usize].0)
+    })
+  );
+}
+
+/// The colors showing on every face: a hexagon's center triangle, then its
+/// trapezoids next to each neighbouring square.
+fn print_faces(puzzle: &Puzzle) {
+  println!("faces:");
+  for hex in Hex::ALL.iter() {
+    let slot = puzzle.triangles.iter().position(|t| t.0 == *hex).unwrap();
+    let mut colors = vec![face_name(puzzle.triangle_at(slot).face())];
+    for (j, edge) in puzzle.edges.iter().enumerate() {
+      if edge.1 == *hex {
+        colors.push(format!(
+          "{:?}:{}",
+          edge.0,
+          face_name(puzzle.edge_at(j).face())
+        ));
+      }
+    }
+    println!("  {:<8} hexagon  {}", format!("{hex:?}"), colors.join(" "));
+  }
+  for square in Square::ALL {
+    let slot = puzzle.squares.iter().position(|s| s.0 == square).unwrap();
+    println!(
+      "  {:<8} square   {}",
+      format!("{square:?}"),
+      face_name(puzzle.square_at(slot).face())
+    );
+  }
+}
+
+fn print_group() {
+  let analysis = group::analyze();
+  let names = |facets: &[u8]| {
+    facets
+      .iter()
+      .map(|f| group::label(*f))
+      .collect::<Vec<_>>()
+      .join(" ")
+  };
+  println!("order:     {}", analysis.order);
+  println!();
+  println!("orbits:");
+  let mut fixed = Vec::new();
+  for orbit in &analysis.orbits {
+    if orbit.facets.len() == 1 {
+      fixed.extend(&orbit.facets);
+      continue;
+    }
+    println!(
+      "  {} facets, {} arrangements, {}: {}",
+      orbit.facets.len(),
+      orbit.order,
+      if orbit.even {
+        "even only"
+      } else {
+        "any parity"
+      },
+      names(&orbit.facets)
+    );
+  }
+  println!("  never move: {}", names(&fixed));
+  println!();
+  println!("twists  states");
+  for (d, count) in analysis.distances.iter().enumerate() {
+    println!("{d:>6}  {count}");
+  }
+  println!("God's number: {}", analysis.gods_number());
+}
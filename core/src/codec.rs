@@ -0,0 +1,250 @@
+use crate::group;
+use crate::permutation::PermArray;
+use crate::puzzle::{edge_facets, square_facets, twist_permutations, Puzzle};
+use crate::state::Twist;
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+/// Number of reachable states; their ranks are the numbers below it.
+/// The five squares that move are evenly permuted, each is turned a half turn or not
+/// with an even number turned, and the two sets of four triangles are evenly permuted.
+pub const STATES: u32 = 60 * (1 << FREE_TURNS) * 12 * 12;
+
+/// Squares whose half turns are free bits of a rank; the last one's follows from theirs.
+const FREE_TURNS: usize = 4;
+
+/// Bytes in `encode`, enough for any rank below `STATES`.
+pub const BYTES: usize = 3;
+
+/// Which facets make up each part of a rank, found from the twists.
+struct Layout {
+  /// The squares twists move.
+  squares: Vec<u8>,
+  /// Two trapezoids of each of those squares that a half turn swaps, lowest first.
+  pairs: Vec<[u8; 2]>,
+  /// The two sets of triangles twists move among each other.
+  triangles: Vec<Vec<u8>>,
+  /// Every reachable edge permutation, by where it sends the `pairs`.
+  edges: HashMap<Vec<u8>, PermArray<24>>,
+}
+
+fn layout() -> &'static Layout {
+  static LAYOUT: OnceLock<Layout> = OnceLock::new();
+  LAYOUT.get_or_init(|| {
+    let twists = Twist::ALL.map(twist_permutations);
+    let edge_twists = twists.clone().map(|t| t.0);
+    let square_twists = twists.clone().map(|t| t.1);
+    let triangle_twists = twists.map(|t| t.2);
+    let squares = moving(group::orbits(&square_twists)).concat();
+    let edge_orbits = moving(group::orbits(&edge_twists));
+    let (edges, squares_facets) = (edge_facets(), square_facets());
+    let pairs: Vec<[u8; 2]> = squares
+      .iter()
+      .map(|s| {
+        let square = squares_facets[*s as usize].0;
+        let pair: Vec<_> = edge_orbits[0]
+          .iter()
+          .copied()
+          .filter(|k| edges[*k as usize].0 == square)
+          .collect();
+        [pair[0], pair[1]]
+      })
+      .collect();
+
+    let key = |e: &PermArray<24>| pairs.iter().flatten().map(|k| e.permute(*k)).collect();
+    let mut reachable = HashMap::from([(key(&PermArray::identity()), PermArray::identity())]);
+    let mut queue = VecDeque::from([PermArray::identity()]);
+    while let Some(e) = queue.pop_front() {
+      for g in &edge_twists {
+        let next = PermArray::compose(&e, g);
+        reachable.entry(key(&next)).or_insert_with(|| {
+          queue.push_back(next.clone());
+          next
+        });
+      }
+    }
+
+    Layout {
+      triangles: moving(group::orbits(&triangle_twists)),
+      squares,
+      pairs,
+      edges: reachable,
+    }
+  })
+}
+
+// orbits without the facets that never move
+fn moving(orbits: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+  orbits.into_iter().filter(|o| o.len() > 1).collect()
+}
+
+/// A number below `STATES` standing for a reachable state, which `unrank` turns back into it.
+/// Lehmer codes rank the permutations of the squares and of each set of triangles,
+/// counting only the even ones, and the half turns of the first `FREE_TURNS` squares
+/// follow as bits, the first one highest.
+pub fn rank(puzzle: &Puzzle) -> Result<u32, String> {
+  let layout = layout();
+  let unreachable = || "no sequence of twists reaches this puzzle".to_string();
+  let squares: PermArray<5> = restrict(&puzzle.square_permutation, &layout.squares)?;
+  let mut rank = squares.rank_even().ok_or_else(unreachable)? as u32;
+  for (s, pair) in layout.pairs.iter().enumerate().take(FREE_TURNS) {
+    let target = layout.pairs[squares.permute(s as u8) as usize];
+    let turned = puzzle.edge_permutation.permute(pair[0]) == target[1];
+    rank = rank * 2 + turned as u32;
+  }
+  for triangles in &layout.triangles {
+    let triangles: PermArray<4> = restrict(&puzzle.triangle_permutation, triangles)?;
+    rank = rank * 12 + triangles.rank_even().ok_or_else(unreachable)? as u32;
+  }
+  // the parts above pin down a reachable state, which has to be this one
+  if unrank(rank)?.to_bytes() != puzzle.to_bytes() {
+    return Err(unreachable());
+  }
+  Ok(rank)
+}
+
+/// The state that `rank` gave a number to.
+pub fn unrank(rank: u32) -> Result<Puzzle, String> {
+  if rank >= STATES {
+    return Err(format!(
+      "{rank} is not below {STATES}, the number of states"
+    ));
+  }
+  let layout = layout();
+  let mut rank = rank as u128;
+  let mut triangles = *PermArray::<8>::identity().as_array();
+  for orbit in layout.triangles.iter().rev() {
+    let p = PermArray::<4>::from_rank_even(rank % 12).unwrap();
+    rank /= 12;
+    for (i, k) in orbit.iter().enumerate() {
+      triangles[*k as usize] = orbit[p.permute(i as u8) as usize];
+    }
+  }
+  let bits = rank % (1 << FREE_TURNS);
+  let squares = PermArray::<5>::from_rank_even(rank >> FREE_TURNS).unwrap();
+
+  let mut turned: Vec<_> = (0..FREE_TURNS)
+    .map(|s| bits >> (FREE_TURNS - 1 - s) & 1 == 1)
+    .collect();
+  // a reachable state has an even number of squares turned
+  turned.push(bits.count_ones() % 2 == 1);
+  let key: Vec<_> = turned
+    .iter()
+    .enumerate()
+    .flat_map(|(s, turned)| {
+      let [low, high] = layout.pairs[squares.permute(s as u8) as usize];
+      if *turned {
+        [high, low]
+      } else {
+        [low, high]
+      }
+    })
+    .collect();
+  let edges = layout.edges[&key].clone();
+  Puzzle::from_permutations(edges, PermArray::from(triangles))
+}
+
+/// `rank` as big endian bytes.
+pub fn encode(puzzle: &Puzzle) -> Result<[u8; BYTES], String> {
+  let bytes = rank(puzzle)?.to_be_bytes();
+  Ok([bytes[1], bytes[2], bytes[3]])
+}
+
+/// Read what `encode` wrote.
+pub fn decode(bytes: &[u8]) -> Result<Puzzle, String> {
+  let bytes: [u8; BYTES] = bytes
+    .try_into()
+    .map_err(|_| format!("expected {BYTES} bytes, got {}", bytes.len()))?;
+  unrank(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+}
+
+// the permutation of `points` among themselves, numbered by their places in `points`
+fn restrict<const N: usize, const M: usize>(
+  p: &PermArray<N>,
+  points: &[u8],
+) -> Result<PermArray<M>, String> {
+  let places = points
+    .iter()
+    .map(|k| {
+      let target = p.permute(*k);
+      points
+        .iter()
+        .position(|k| *k == target)
+        .map(|place| place as u8)
+        .ok_or("no sequence of twists reaches this puzzle")
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  PermArray::try_from(&places[..])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::scramble::Rng;
+
+  #[test]
+  fn layout_matches_the_group() {
+    let layout = layout();
+    assert_eq!(layout.squares.len(), 5);
+    assert_eq!(layout.triangles, [vec![0, 1, 2, 3], vec![4, 5, 6, 7]]);
+    assert_eq!(layout.edges.len(), 960);
+    assert_eq!(STATES as u128, group::chain().order());
+  }
+
+  #[test]
+  fn solved_ranks_zero() {
+    assert_eq!(rank(&Puzzle::new()), Ok(0));
+    assert!(unrank(0).unwrap().is_solved());
+  }
+
+  #[test]
+  fn every_rank_round_trips() {
+    let mut seen = std::collections::HashSet::new();
+    for r in 0..STATES {
+      let puzzle = unrank(r).unwrap();
+      assert_eq!(rank(&puzzle), Ok(r));
+      seen.insert(puzzle.to_bytes());
+    }
+    assert_eq!(seen.len(), STATES as usize);
+    assert!(unrank(STATES).is_err());
+  }
+
+  #[test]
+  fn random_states_round_trip() {
+    let mut rng = Rng::new(7);
+    for _ in 0..200 {
+      let element = group::chain().random(|n| rng.below(n));
+      let puzzle = group::puzzle(&element);
+      let bytes = encode(&puzzle).unwrap();
+      assert_eq!(decode(&bytes).unwrap().to_bytes(), puzzle.to_bytes());
+    }
+    assert!(decode(&[0, 0]).is_err());
+    assert!(decode(&[0xff, 0xff, 0xff]).is_err());
+  }
+
+  #[test]
+  fn rejects_unreachable_states() {
+    let mut puzzle = Puzzle::new();
+    puzzle.triangle_permutation = PermArray::from([1, 0, 2, 3, 4, 5, 6, 7]);
+    assert!(rank(&puzzle).is_err());
+    let mut puzzle = Puzzle::new();
+    puzzle.triangle_permutation = PermArray::from([4, 1, 2, 3, 0, 5, 6, 7]);
+    assert!(rank(&puzzle).is_err());
+
+    // a single square turned a half turn
+    let puzzle =
+      Puzzle::from_permutations(half_turn_of_last_square(), PermArray::identity()).unwrap();
+    assert!(rank(&puzzle).is_err());
+  }
+
+  // swap the paired trapezoids of one square, and the other two with them
+  fn half_turn_of_last_square() -> PermArray<24> {
+    let layout = layout();
+    let [a, b] = layout.pairs[4];
+    let square = edge_facets()[a as usize].0;
+    let others: Vec<_> = (0..24u8)
+      .filter(|k| *k != a && *k != b && edge_facets()[*k as usize].0 == square)
+      .collect();
+    PermArray::from_cycles(&[vec![a, b], others]).unwrap()
+  }
+}
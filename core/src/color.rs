@@ -0,0 +1,279 @@
+use crate::puzzle::Face;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+  red: u8,
+  green: u8,
+  blue: u8,
+  alpha: f32,
+}
+
+impl Color {
+  pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
+    Color {
+      red,
+      green,
+      blue,
+      alpha: 1.,
+    }
+  }
+  pub const fn rgba(red: u8, green: u8, blue: u8, alpha: f32) -> Self {
+    Color {
+      red,
+      green,
+      blue,
+      alpha,
+    }
+  }
+
+  /// Parse a css style hex color, e.g. `#ff8800`.
+  pub fn from_hex(hex: &str) -> Result<Self, String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let channel = |i: usize| {
+      digits
+        .get(2 * i..2 * i + 2)
+        .and_then(|s| u8::from_str_radix(s, 16).ok())
+    };
+    match (digits.len(), channel(0), channel(1), channel(2)) {
+      (6, Some(red), Some(green), Some(blue)) => Ok(Self::rgb(red, green, blue)),
+      _ => Err(format!("invalid color: {hex}")),
+    }
+  }
+
+  /// The css style hex color `from_hex` reads, ignoring alpha.
+  pub fn to_hex(&self) -> String {
+    format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+  }
+
+  pub fn red(&self) -> f32 {
+    f32::from(self.red) / 255.
+  }
+
+  pub fn green(&self) -> f32 {
+    f32::from(self.green) / 255.
+  }
+
+  pub fn blue(&self) -> f32 {
+    f32::from(self.blue) / 255.
+  }
+
+  pub fn alpha(&self) -> f32 {
+    self.alpha
+  }
+
+  pub const MAGENTA: Self = Self::rgb(210, 75, 208);
+  pub const SILVER: Self = Self::rgb(143, 143, 143);
+  pub const BLUE_VIOLET: Self = Self::rgb(119, 153, 252);
+  pub const SKY_BLUE: Self = Self::rgb(135, 206, 235);
+  pub const WHITE: Self = Self::rgb(212, 241, 252);
+  pub const LIGHT_GREEN: Self = Self::rgb(221, 250, 220);
+  pub const CORAL: Self = Self::rgb(244, 79, 130);
+  pub const RED: Self = Self::rgb(173, 25, 2);
+  pub const LIGHT_RED: Self = Self::rgb(232, 173, 191);
+  pub const PINK: Self = Self::rgb(247, 143, 198);
+  pub const LIGHT_PINK: Self = Self::rgb(252, 222, 255);
+  pub const LIGHT_PURPLE: Self = Self::rgb(196, 173, 234);
+  pub const CYAN: Self = Self::rgb(12, 249, 239);
+  pub const TEAL: Self = Self::rgb(33, 209, 163);
+  pub const VIOLET: Self = Self::rgb(94, 79, 160);
+  pub const YELLOW: Self = Self::rgb(178, 201, 43);
+  pub const BLUE: Self = Self::rgb(41, 67, 163);
+  pub const GREEN: Self = Self::rgb(35, 118, 49);
+  pub const ORANGE: Self = Self::rgb(235, 135, 21);
+}
+
+/// Assigns a color to each face of the puzzle.
+/// Faces are named after their color in the Dayan Gem IV factory scheme,
+/// so other schemes are a recoloring of those names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorScheme {
+  pub name: String,
+  /// indexed in the order of `Square::ALL`
+  pub squares: [Color; 6],
+  /// indexed in the order of `Hex::ALL`
+  pub hexes: [Color; 8],
+}
+
+impl ColorScheme {
+  pub fn color(&self, face: Face) -> Color {
+    match face {
+      Face::Square(square) => self.squares[square as usize],
+      Face::Hex(hex) => self.hexes[hex as usize],
+    }
+  }
+
+  pub fn dayan_gem_iv() -> Self {
+    Self {
+      name: String::from("dayan-gem-iv"),
+      squares: [
+        Color::WHITE,
+        Color::YELLOW,
+        Color::BLUE,
+        Color::GREEN,
+        Color::RED,
+        Color::ORANGE,
+      ],
+      hexes: [
+        Color::WHITE,
+        Color::PINK,
+        Color::RED,
+        Color::BLUE,
+        Color::YELLOW,
+        Color::SILVER,
+        Color::ORANGE,
+        Color::GREEN,
+      ],
+    }
+  }
+
+  /// Fully saturated versions of the factory colors.
+  pub fn high_contrast() -> Self {
+    let white = Color::rgb(255, 255, 255);
+    let yellow = Color::rgb(255, 230, 0);
+    let blue = Color::rgb(0, 60, 255);
+    let green = Color::rgb(0, 190, 40);
+    let red = Color::rgb(230, 0, 0);
+    let orange = Color::rgb(255, 120, 0);
+    Self {
+      name: String::from("high-contrast"),
+      squares: [white, yellow, blue, green, red, orange],
+      hexes: [
+        white,
+        Color::rgb(255, 0, 200),
+        red,
+        blue,
+        yellow,
+        Color::rgb(110, 110, 110),
+        orange,
+        green,
+      ],
+    }
+  }
+
+  /// The pastel colors this simulator originally shipped with.
+  pub fn classic() -> Self {
+    Self {
+      name: String::from("classic"),
+      squares: [
+        Color::VIOLET,
+        Color::BLUE_VIOLET,
+        Color::TEAL,
+        Color::SKY_BLUE,
+        Color::LIGHT_RED,
+        Color::CYAN,
+      ],
+      hexes: [
+        Color::SILVER,
+        Color::ORANGE,
+        Color::GREEN,
+        Color::YELLOW,
+        Color::MAGENTA,
+        Color::CORAL,
+        Color::BLUE,
+        Color::WHITE,
+      ],
+    }
+  }
+
+  /// Okabe & Ito's palette, whose colors stay distinct without red sensitive cones.
+  pub fn protanopia() -> Self {
+    Self::by_color_name(
+      "protanopia",
+      [
+        Color::rgb(255, 255, 255),
+        Color::rgb(204, 121, 167),
+        Color::rgb(213, 94, 0),
+        Color::rgb(0, 114, 178),
+        Color::rgb(240, 228, 66),
+        Color::rgb(86, 180, 233),
+        Color::rgb(230, 159, 0),
+        Color::rgb(0, 158, 115),
+      ],
+    )
+  }
+
+  /// Contrasts blue against orange and spreads the rest by lightness,
+  /// since deuteranopes can't separate red from green.
+  pub fn deuteranopia() -> Self {
+    Self::by_color_name(
+      "deuteranopia",
+      [
+        Color::rgb(255, 255, 255),
+        Color::rgb(220, 38, 127),
+        Color::rgb(254, 97, 0),
+        Color::rgb(100, 143, 255),
+        Color::rgb(255, 176, 0),
+        Color::rgb(120, 94, 240),
+        Color::rgb(255, 221, 153),
+        Color::rgb(40, 70, 150),
+      ],
+    )
+  }
+
+  /// Contrasts red against cyan and avoids mixing blues, greens and yellows,
+  /// which tritanopes confuse.
+  pub fn tritanopia() -> Self {
+    Self::by_color_name(
+      "tritanopia",
+      [
+        Color::rgb(255, 255, 255),
+        Color::rgb(255, 109, 182),
+        Color::rgb(200, 0, 30),
+        Color::rgb(0, 109, 109),
+        Color::rgb(36, 255, 200),
+        Color::rgb(109, 109, 109),
+        Color::rgb(146, 73, 0),
+        Color::rgb(255, 182, 119),
+      ],
+    )
+  }
+
+  pub fn builtin() -> Vec<Self> {
+    vec![
+      Self::dayan_gem_iv(),
+      Self::high_contrast(),
+      Self::classic(),
+      Self::protanopia(),
+      Self::deuteranopia(),
+      Self::tritanopia(),
+    ]
+  }
+
+  /// A scheme where faces with the same factory color name share a color,
+  /// listed in the order of `Hex::ALL`.
+  fn by_color_name(name: &str, hexes: [Color; 8]) -> Self {
+    let [white, _pink, red, blue, yellow, _silver, orange, green] = hexes;
+    Self {
+      name: String::from(name),
+      squares: [white, yellow, blue, green, red, orange],
+      hexes,
+    }
+  }
+
+  /// Build a scheme from css hex colors,
+  /// listed in the order of `Square::ALL` and `Hex::ALL` respectively.
+  pub fn from_hex(name: String, squares: &[String], hexes: &[String]) -> Result<Self, String> {
+    fn parse<const N: usize>(colors: &[String]) -> Result<[Color; N], String> {
+      let colors = colors
+        .iter()
+        .map(|c| Color::from_hex(c))
+        .collect::<Result<Vec<_>, _>>()?;
+      let len = colors.len();
+      colors
+        .try_into()
+        .map_err(|_| format!("expected {N} colors, got {len}"))
+    }
+    Ok(Self {
+      name,
+      squares: parse(squares)?,
+      hexes: parse(hexes)?,
+    })
+  }
+}
+
+/// The pastel palette the puzzle has always been drawn with.
+impl Default for ColorScheme {
+  fn default() -> Self {
+    Self::classic()
+  }
+}
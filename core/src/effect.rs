@@ -0,0 +1,200 @@
+use crate::group;
+use crate::notation;
+use crate::permutation::{format_cycles, PermArray};
+use crate::puzzle::Puzzle;
+use crate::state::Twist;
+use std::fmt;
+
+/// What a move sequence does to a solved puzzle.
+pub struct Effect {
+  /// Cycles of square pieces, numbered as `Puzzle::squares`.
+  pub squares: Vec<Vec<u8>>,
+  /// Cycles of triangles, numbered as `Puzzle::triangles`.
+  pub triangles: Vec<Vec<u8>>,
+  /// Cycles of the trapezoids the square pieces carry, numbered as `Puzzle::edges`.
+  pub edges: Vec<Vec<u8>>,
+  /// Squares that come back turned, either in place or once around their cycle.
+  /// Twists only ever turn a square half way round.
+  pub reoriented: Vec<u8>,
+  /// Repetitions of the sequence that return the puzzle to solved.
+  pub order: usize,
+  /// `A` and `B` when the sequence is written as `A B A' B'`, as it stands
+  /// or once `notation::simplify` has merged twists, so `H J J' J H' J'` counts.
+  /// Sequences that only equal a commutator as permutations aren't found.
+  pub commutator: Option<(Vec<Twist>, Vec<Twist>)>,
+}
+
+pub fn analyze(twists: &[Twist]) -> Effect {
+  let mut puzzle = Puzzle::new();
+  for twist in twists {
+    puzzle.twist(*twist);
+  }
+  let order = group::order(&group::element(&puzzle));
+  Effect {
+    squares: puzzle.square_permutation.cycles(),
+    triangles: puzzle.triangle_permutation.cycles(),
+    edges: puzzle.edge_permutation.cycles(),
+    reoriented: reoriented(&puzzle),
+    order,
+    commutator: commutator(twists).or_else(|| commutator(&notation::simplify(twists))),
+  }
+}
+
+/// One line per item, naming pieces by the faces of their slots when solved.
+impl fmt::Display for Effect {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let puzzle = Puzzle::new();
+    let square = |k: u8| format!("{:?}", puzzle.squares[k as usize].0);
+    let triangle = |k: u8| format!("{:?}", puzzle.triangles[k as usize].0);
+    let edge = |k: u8| {
+      let edge = puzzle.edges[k as usize];
+      format!("{:?}-{:?}", edge.0, edge.1)
+    };
+    writeln!(f, "squares:     {}", format_cycles(&self.squares, square))?;
+    writeln!(
+      f,
+      "triangles:   {}",
+      format_cycles(&self.triangles, triangle)
+    )?;
+    writeln!(f, "edges:       {}", format_cycles(&self.edges, edge))?;
+    let reoriented: Vec<_> = self.reoriented.iter().map(|k| square(*k)).collect();
+    writeln!(
+      f,
+      "reoriented:  {}",
+      if reoriented.is_empty() {
+        "none".to_string()
+      } else {
+        reoriented.join(" ")
+      }
+    )?;
+    writeln!(f, "order:       {}", self.order)?;
+    match &self.commutator {
+      Some((a, b)) => writeln!(
+        f,
+        "commutator:  [{}, {}]",
+        notation::format(a),
+        notation::format(b)
+      ),
+      None => writeln!(f, "commutator:  no"),
+    }
+  }
+}
+
+// a square is turned when its trapezoids have not come home by the time the square has
+fn reoriented(puzzle: &Puzzle) -> Vec<u8> {
+  let mut reoriented = Vec::new();
+  for (k, square) in puzzle.squares.iter().enumerate() {
+    let mut length = 1;
+    let mut slot = puzzle.square_permutation.permute(k as u8);
+    while slot != k as u8 {
+      slot = puzzle.square_permutation.permute(slot);
+      length += 1;
+    }
+    let mut edges = PermArray::identity();
+    for _ in 0..length {
+      edges = PermArray::compose(&edges, &puzzle.edge_permutation);
+    }
+    let turned = puzzle
+      .edges
+      .iter()
+      .enumerate()
+      .any(|(i, edge)| edge.0 == square.0 && edges.permute(i as u8) != i as u8);
+    if turned {
+      reoriented.push(k as u8);
+    }
+  }
+  reoriented
+}
+
+fn commutator(twists: &[Twist]) -> Option<(Vec<Twist>, Vec<Twist>)> {
+  if !twists.len().is_multiple_of(2) {
+    return None;
+  }
+  let half = twists.len() / 2;
+  (1..half).find_map(|split| {
+    let (a, b) = (&twists[..split], &twists[split..half]);
+    let rest = &twists[half..];
+    (rest[..split] == notation::inverse(a)[..] && rest[split..] == notation::inverse(b)[..])
+      .then(|| (a.to_vec(), b.to_vec()))
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn effect(moves: &str) -> Effect {
+    analyze(&notation::parse(moves).unwrap())
+  }
+
+  #[test]
+  fn single_twist() {
+    let effect = effect("H");
+    assert_eq!(effect.squares.len(), 1);
+    // the three squares that move each carry four trapezoids
+    assert_eq!(effect.edges.len(), 4);
+    assert!(effect.edges.iter().all(|cycle| cycle.len() == 3));
+    assert_eq!(effect.squares[0].len(), 3);
+    assert_eq!(effect.triangles.len(), 1);
+    assert!(effect.reoriented.is_empty());
+    assert_eq!(effect.order, 3);
+    assert!(effect.commutator.is_none());
+  }
+
+  #[test]
+  fn solved_has_no_effect() {
+    let effect = effect("H H H");
+    assert!(effect.squares.is_empty());
+    assert!(effect.triangles.is_empty());
+    assert!(effect.edges.is_empty());
+    assert_eq!(effect.order, 1);
+    assert_eq!(effect.to_string().lines().next(), Some("squares:     ()"));
+  }
+
+  #[test]
+  fn order_returns_to_solved() {
+    let twists = notation::parse("H J' K L K'").unwrap();
+    let effect = analyze(&twists);
+    let mut puzzle = Puzzle::new();
+    for k in 1..=effect.order {
+      for twist in &twists {
+        puzzle.twist(*twist);
+      }
+      assert_eq!(puzzle.is_solved(), k == effect.order);
+    }
+  }
+
+  #[test]
+  fn finds_commutators() {
+    let effect = effect("H J H' J'");
+    let (a, b) = effect.commutator.as_ref().unwrap();
+    assert_eq!(notation::format(a), "H");
+    assert_eq!(notation::format(b), "J");
+    assert!(effect.to_string().contains("commutator:  [H, J]"));
+    assert!(analyze(&notation::parse("H J K' J'").unwrap())
+      .commutator
+      .is_none());
+    assert!(analyze(&[]).commutator.is_none());
+
+    let (a, b) = analyze(&notation::parse("H J J' J H' J'").unwrap())
+      .commutator
+      .unwrap();
+    assert_eq!(
+      (notation::format(&a), notation::format(&b)),
+      ("H".into(), "J".into())
+    );
+  }
+
+  #[test]
+  fn reoriented_squares() {
+    // the blue square stays put but turns half way round,
+    // and the white, orange and yellow ones come back turned
+    let effect = effect("H2 J2");
+    assert_eq!(effect.squares, [vec![0, 5, 1]]);
+    assert_eq!(effect.reoriented, [0, 1, 2, 5]);
+    assert_eq!(effect.order, 6);
+    assert!(effect
+      .to_string()
+      .contains("reoriented:  White Yellow Blue Orange\n"));
+  }
+}
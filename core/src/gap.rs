@@ -0,0 +1,171 @@
+use crate::group::{self, FACETS};
+use crate::notation::{self, LETTERS};
+use crate::permutation;
+use crate::state::Twist;
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The twist group as a GAP program.
+/// Points are the facets of `group::generators`, counted from 1 as GAP does,
+/// and the comments list which facet each point is.
+/// The generators are named after the moves, and `hom` maps words in them onto the group,
+/// so `PreImagesRepresentative(hom, g)` gives a word that `import` reads back.
+pub fn export() -> String {
+  let generators = group::generators();
+  let mut out = String::from("# points are facets:\n");
+  for k in 0..FACETS as u8 {
+    let _ = writeln!(out, "# {:>3}  {}", k + 1, group::label(k));
+  }
+  out.push('\n');
+  for (letter, generator) in LETTERS.iter().zip(&generators) {
+    let _ = writeln!(out, "{letter} := {};", cycles(&generator.cycles()));
+  }
+  let letters = LETTERS.map(|l| l.to_string());
+  let _ = writeln!(out, "G := Group({});", letters.join(", "));
+  let names: Vec<_> = letters.iter().map(|l| format!("\"{l}\"")).collect();
+  let _ = writeln!(
+    out,
+    "hom := EpimorphismFromFreeGroup(G : names := [{}]);",
+    names.join(", ")
+  );
+  let _ = writeln!(out, "# Size(G) = {}", group::chain().order());
+  out
+}
+
+/// A GAP permutation, e.g. `(1,2,3)(4,5)` for the points `0 1 2` and `3 4`.
+pub fn cycles(cycles: &[Vec<u8>]) -> String {
+  permutation::format_cycles(cycles, |k| (k + 1).to_string()).replace(' ', ",")
+}
+
+/// Turn a word in the generators of `export`, such as `H*J^-1*(K*L)^2`, into twists.
+/// Each generator has order 3, so its powers come out as at most one twist.
+pub fn import(word: &str) -> Result<Vec<Twist>, String> {
+  let word = word.trim();
+  if word.starts_with("<identity") {
+    return Ok(Vec::new());
+  }
+  let mut chars = word.chars().peekable();
+  let twists = product(&mut chars)?;
+  match skip_space(&mut chars) {
+    None => Ok(twists),
+    Some(c) => Err(format!("unexpected `{c}` in `{word}`")),
+  }
+}
+
+// factors joined by `*`
+fn product(chars: &mut Peekable<Chars>) -> Result<Vec<Twist>, String> {
+  let mut twists = factor(chars)?;
+  while skip_space(chars) == Some('*') {
+    chars.next();
+    twists.extend(factor(chars)?);
+  }
+  Ok(twists)
+}
+
+// a generator or a parenthesized product, with an optional power
+fn factor(chars: &mut Peekable<Chars>) -> Result<Vec<Twist>, String> {
+  skip_space(chars);
+  let twists = match chars.next() {
+    Some('(') => {
+      let twists = product(chars)?;
+      if skip_space(chars) != Some(')') {
+        return Err("unclosed `(`".to_string());
+      }
+      chars.next();
+      twists
+    }
+    Some(c) => match LETTERS.iter().position(|l| l.eq_ignore_ascii_case(&c)) {
+      Some(index) => vec![Twist::ALL[index]],
+      None => return Err(format!("unknown generator `{c}`")),
+    },
+    None => return Err("the word ends too early".to_string()),
+  };
+
+  if skip_space(chars) != Some('^') {
+    return Ok(twists);
+  }
+  chars.next();
+  skip_space(chars);
+  let mut exponent = String::new();
+  while let Some(&c) = chars.peek() {
+    if !(c.is_ascii_digit() || c == '-' && exponent.is_empty()) {
+      break;
+    }
+    exponent.push(c);
+    chars.next();
+  }
+  let exponent: i64 = exponent
+    .parse()
+    .map_err(|_| format!("bad exponent `{exponent}`"))?;
+
+  if let [twist] = twists[..] {
+    return Ok(match exponent.rem_euclid(3) {
+      0 => vec![],
+      1 => vec![twist],
+      _ => vec![twist.inverse()],
+    });
+  }
+  notation::power(&twists, exponent)
+}
+
+// GAP breaks long words with a backslash at the end of the line
+fn skip_space(chars: &mut Peekable<Chars>) -> Option<char> {
+  while let Some(&c) = chars.peek() {
+    if !(c.is_whitespace() || c == '\\') {
+      break;
+    }
+    chars.next();
+  }
+  chars.peek().copied()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::permutation::PermArray;
+
+  #[test]
+  fn exports_generators() {
+    let text = export();
+    assert!(text.contains("#   1  Blue-White\n"));
+    assert!(text.contains("G := Group(H, J, K, L);\n"));
+    assert!(text.contains("# Size(G) = 138240\n"));
+
+    let generators = group::generators();
+    for (letter, generator) in LETTERS.iter().zip(&generators) {
+      let line = text
+        .lines()
+        .find(|line| line.starts_with(&format!("{letter} := ")))
+        .unwrap();
+      let gap = line[5..line.len() - 1].to_string();
+      let cycles = permutation::parse_cycles(&gap, |k| k.parse::<u8>().ok()?.checked_sub(1));
+      assert_eq!(
+        PermArray::<FACETS>::from_cycles(&cycles.unwrap()).as_ref(),
+        Ok(generator)
+      );
+    }
+  }
+
+  #[test]
+  fn imports_words() {
+    let twists = |moves| notation::parse(moves).unwrap();
+    assert_eq!(import("H*J^-1*K^2").unwrap(), twists("H J' K'"));
+    assert_eq!(import("(H*J)^2").unwrap(), twists("H J H J"));
+    assert_eq!(import("(H*J)^-1").unwrap(), twists("J' H'"));
+    assert_eq!(import("L^3").unwrap(), []);
+    assert!(import("(H*J)^999999999999").is_err());
+    assert_eq!(import("H^999999999999").unwrap(), []);
+    assert_eq!(import("H*\\\n  J").unwrap(), twists("H J"));
+    assert_eq!(import("<identity ...>").unwrap(), []);
+  }
+
+  #[test]
+  fn rejects_bad_words() {
+    assert!(import("H*X").is_err());
+    assert!(import("(H*J").is_err());
+    assert!(import("H^").is_err());
+    assert!(import("H J").is_err());
+    assert!(import("H*").is_err());
+  }
+}
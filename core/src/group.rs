@@ -0,0 +1,294 @@
+use crate::permutation::{self, PermArray, StabilizerChain};
+use crate::puzzle::{edge_facets, square_facets, triangle_facets, Puzzle};
+use crate::state::Twist;
+use std::collections::{HashMap, VecDeque};
+use std::sync::OnceLock;
+
+/// Every facet of the puzzle numbered as one set: the edges, then the squares,
+/// then the triangles, each in the order of `puzzle::Puzzle`.
+pub const FACETS: usize = 24 + 6 + 8;
+
+const SQUARES: usize = 24;
+const TRIANGLES: usize = 24 + 6;
+
+/// The twist group acting on the puzzle's facets.
+pub struct Analysis {
+  /// Number of reachable states.
+  pub order: u128,
+  pub orbits: Vec<Orbit>,
+  /// How many states lie each number of twists from solved.
+  pub distances: Vec<usize>,
+}
+
+/// Facets that twists can move into each other's places.
+pub struct Orbit {
+  pub facets: Vec<u8>,
+  /// Size of the group acting on these facets alone.
+  pub order: u128,
+  /// Whether every twist permutes these facets evenly,
+  /// so no state has an odd permutation of them.
+  pub even: bool,
+}
+
+impl Analysis {
+  /// Twists needed for the hardest states.
+  pub fn gods_number(&self) -> usize {
+    self.distances.len() - 1
+  }
+}
+
+/// The eight twists as permutations of all facets.
+pub fn generators() -> [PermArray<FACETS>; 8] {
+  Twist::ALL.map(|twist| {
+    let mut puzzle = Puzzle::new();
+    puzzle.twist(twist);
+    element(&puzzle)
+  })
+}
+
+/// Where every facet of the puzzle is, numbered as in `generators`.
+pub fn element(puzzle: &Puzzle) -> PermArray<FACETS> {
+  PermArray::from(std::array::from_fn(|k| {
+    if k < SQUARES {
+      puzzle.edge_permutation.permute(k as u8)
+    } else if k < TRIANGLES {
+      SQUARES as u8 + puzzle.square_permutation.permute((k - SQUARES) as u8)
+    } else {
+      TRIANGLES as u8 + puzzle.triangle_permutation.permute((k - TRIANGLES) as u8)
+    }
+  }))
+}
+
+/// The puzzle with its facets where `element` says.
+pub fn puzzle(element: &PermArray<FACETS>) -> Puzzle {
+  let slots = element.as_array();
+  let offset =
+    |slots: &[u8], start: usize| slots.iter().map(|s| s - start as u8).collect::<Vec<_>>();
+  let mut puzzle = Puzzle::new();
+  puzzle.edge_permutation = PermArray::try_from(&slots[..SQUARES]).unwrap();
+  puzzle.square_permutation =
+    PermArray::try_from(&offset(&slots[SQUARES..TRIANGLES], SQUARES)[..]).unwrap();
+  puzzle.triangle_permutation =
+    PermArray::try_from(&offset(&slots[TRIANGLES..], TRIANGLES)[..]).unwrap();
+  puzzle
+}
+
+/// The group of reachable states, built once.
+pub fn chain() -> &'static StabilizerChain<PermArray<FACETS>> {
+  static CHAIN: OnceLock<StabilizerChain<PermArray<FACETS>>> = OnceLock::new();
+  CHAIN.get_or_init(|| StabilizerChain::new(&generators()))
+}
+
+/// Whether some sequence of twists leads to this state, e.g. one edited by hand.
+pub fn is_reachable(puzzle: &Puzzle) -> bool {
+  chain().contains(&element(puzzle))
+}
+
+/// How many times an element has to be applied to get back to the identity.
+pub fn order(element: &PermArray<FACETS>) -> usize {
+  element
+    .cycles()
+    .iter()
+    .fold(1, |order, cycle| lcm(order, cycle.len()))
+}
+
+/// Name of a facet, numbered as in `generators`.
+pub fn label(facet: u8) -> String {
+  let k = facet as usize;
+  if k < SQUARES {
+    let edge = edge_facets()[k];
+    format!("{:?}-{:?}", edge.0, edge.1)
+  } else if k < TRIANGLES {
+    format!("{:?}-square", square_facets()[k - SQUARES].0)
+  } else {
+    format!("{:?}-triangle", triangle_facets()[k - TRIANGLES].0)
+  }
+}
+
+/// The facet a `label` names.
+pub fn facet(label: &str) -> Option<u8> {
+  (0..FACETS as u8).find(|k| self::label(*k).eq_ignore_ascii_case(label))
+}
+
+/// A state as labeled cycles, e.g. `(Blue-White Yellow-Orange Green-White)`.
+pub fn format(element: &PermArray<FACETS>) -> String {
+  permutation::format_cycles(&element.cycles(), label)
+}
+
+/// Read labeled cycles written by `format`.
+pub fn parse(text: &str) -> Result<PermArray<FACETS>, String> {
+  PermArray::from_cycles(&permutation::parse_cycles(text, facet)?)
+}
+
+pub fn analyze() -> Analysis {
+  let generators = generators();
+  let orbits = orbits(&generators)
+    .into_iter()
+    .map(|facets| {
+      let restricted: Vec<_> = generators.iter().map(|g| restrict(g, &facets)).collect();
+      Orbit {
+        order: StabilizerChain::new(&restricted).order(),
+        even: restricted.iter().all(PermArray::is_even),
+        facets,
+      }
+    })
+    .collect();
+  Analysis {
+    order: chain().order(),
+    orbits,
+    distances: distances(&generators),
+  }
+}
+
+/// Partition the points into the sets the generators move among each other.
+pub fn orbits<const N: usize>(generators: &[PermArray<N>]) -> Vec<Vec<u8>> {
+  let mut seen = [false; N];
+  let mut orbits = Vec::new();
+  for start in 0..N {
+    if seen[start] {
+      continue;
+    }
+    seen[start] = true;
+    let mut orbit = vec![start as u8];
+    let mut i = 0;
+    while i < orbit.len() {
+      for g in generators {
+        let next = g.permute(orbit[i]);
+        if !seen[next as usize] {
+          seen[next as usize] = true;
+          orbit.push(next);
+        }
+      }
+      i += 1;
+    }
+    orbit.sort();
+    orbits.push(orbit);
+  }
+  orbits
+}
+
+/// Breadth first search from solved over every reachable state.
+pub fn distances<const N: usize>(generators: &[PermArray<N>]) -> Vec<usize> {
+  let mut seen = HashMap::from([(PermArray::identity(), 0)]);
+  let mut queue = VecDeque::from([PermArray::identity()]);
+  let mut counts = vec![];
+  while let Some(state) = queue.pop_front() {
+    let d = seen[&state];
+    if counts.len() <= d {
+      counts.push(0);
+    }
+    counts[d] += 1;
+    for g in generators {
+      let next = PermArray::compose(&state, g);
+      if !seen.contains_key(&next) {
+        seen.insert(next.clone(), d + 1);
+        queue.push_back(next);
+      }
+    }
+  }
+  counts
+}
+
+fn lcm(a: usize, b: usize) -> usize {
+  let (mut x, mut y) = (a, b);
+  while y != 0 {
+    (x, y) = (y, x % y);
+  }
+  a / x * b
+}
+
+// identity outside of the given points
+fn restrict<const N: usize>(g: &PermArray<N>, points: &[u8]) -> PermArray<N> {
+  let mut array = *PermArray::<N>::identity().as_array();
+  for &k in points {
+    array[k as usize] = g.permute(k);
+  }
+  PermArray::from(array)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn puzzle_group() {
+    let analysis = analyze();
+    assert_eq!(analysis.order, 138240);
+
+    // every twist axis points to positive z, so the red square never moves;
+    // the other squares carry two orbits of trapezoids,
+    // half a turn apart, and never flip the halves over
+    let sizes: Vec<_> = analysis.orbits.iter().map(|o| o.facets.len()).collect();
+    assert_eq!(sizes, [10, 10, 1, 1, 1, 1, 5, 1, 4, 4]);
+    let orders: Vec<_> = analysis.orbits.iter().map(|o| o.order).collect();
+    assert_eq!(orders, [960, 960, 1, 1, 1, 1, 60, 1, 12, 12]);
+    assert!(analysis.orbits.iter().all(|o| o.even));
+
+    assert_eq!(
+      analysis.distances,
+      [1, 8, 48, 288, 1632, 8568, 36114, 74799, 16547, 220, 15]
+    );
+    assert_eq!(
+      analysis.distances.iter().sum::<usize>() as u128,
+      analysis.order
+    );
+    assert_eq!(analysis.gods_number(), 10);
+  }
+
+  #[test]
+  fn reachable_states() {
+    let mut puzzle = Puzzle::new();
+    assert!(is_reachable(&puzzle));
+    for twist in [Twist::ALL[1], Twist::ALL[6], Twist::ALL[3]] {
+      puzzle.twist(twist);
+    }
+    assert!(is_reachable(&puzzle));
+
+    // swapping two triangles is odd
+    let mut swapped = puzzle.clone();
+    swapped.triangle_permutation = PermArray::compose(
+      &puzzle.triangle_permutation,
+      &PermArray::from([1, 0, 2, 3, 4, 5, 6, 7]),
+    );
+    assert!(!is_reachable(&swapped));
+
+    // the red square never moves
+    let mut moved = Puzzle::new();
+    moved.square_permutation = PermArray::from([0, 1, 2, 5, 3, 4]);
+    assert!(!is_reachable(&moved));
+  }
+
+  #[test]
+  fn random_elements_are_reachable_states() {
+    let mut k = 0;
+    for _ in 0..20 {
+      let g = chain().random(|n| {
+        k += 7;
+        k % n
+      });
+      assert!(chain().contains(&g));
+    }
+  }
+
+  #[test]
+  fn labels() {
+    assert_eq!(label(0), "Blue-White");
+    assert_eq!(label(24), "White-square");
+    assert_eq!(label(30), "White-triangle");
+    for k in 0..FACETS as u8 {
+      assert_eq!(facet(&label(k)), Some(k));
+    }
+    assert_eq!(facet("Nowhere"), None);
+  }
+
+  #[test]
+  fn labeled_cycles_round_trip() {
+    let mut puzzle = Puzzle::new();
+    puzzle.twist(Twist::ALL[0]);
+    let g = element(&puzzle);
+    let text = format(&g);
+    assert!(text.starts_with("(Blue-White "), "{text}");
+    assert_eq!(parse(&text), Ok(g));
+    assert_eq!(format(&PermArray::identity()), "()");
+  }
+}
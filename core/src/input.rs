@@ -0,0 +1,201 @@
+use crate::state::{State, Twist};
+use std::collections::HashMap;
+
+pub type Keymap = HashMap<String, Command>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+  X,
+  Y,
+  Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+  Positive,
+  Negative,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+  Camera {
+    axis: Axis,
+    orientation: Orientation,
+  },
+  Twist {
+    // bit pattern.
+    // first bit is the x axis,
+    // second bit is the y axis,
+    // third bit is orientation
+    octant: u8,
+  },
+  /// Take back the last twist.
+  Undo,
+  /// Start recording twists, or stop and keep them as the next macro.
+  Record,
+  /// Play a macro from `State::macros`, or its inverse.
+  Macro { index: usize, inverted: bool },
+}
+
+impl Command {
+  pub fn camera(orientation: Orientation, axis: Axis) -> Self {
+    Self::Camera { orientation, axis }
+  }
+
+  pub fn twist(x: bool, y: bool, z: bool) -> Self {
+    let mut octant = 0;
+    if x {
+      octant |= 1
+    }
+    if y {
+      octant |= 2
+    }
+    if z {
+      octant |= 4
+    }
+    Self::Twist { octant }
+  }
+}
+
+impl Axis {
+  fn index(self) -> usize {
+    match self {
+      Axis::X => 0,
+      Axis::Y => 1,
+      Axis::Z => 2,
+    }
+  }
+}
+
+pub fn default_keymap() -> Keymap {
+  let mut keymap: Keymap = [
+    ("w", Command::camera(Orientation::Negative, Axis::X)),
+    ("a", Command::camera(Orientation::Negative, Axis::Y)),
+    ("s", Command::camera(Orientation::Positive, Axis::X)),
+    ("d", Command::camera(Orientation::Positive, Axis::Y)),
+    ("q", Command::camera(Orientation::Positive, Axis::Z)),
+    ("e", Command::camera(Orientation::Negative, Axis::Z)),
+    ("h", Command::twist(true, true, true)),
+    ("j", Command::twist(true, false, true)),
+    ("k", Command::twist(false, false, true)),
+    ("l", Command::twist(false, true, true)),
+    ("y", Command::twist(true, true, false)),
+    ("u", Command::twist(true, false, false)),
+    ("i", Command::twist(false, false, false)),
+    ("o", Command::twist(false, true, false)),
+    ("Backspace", Command::Undo),
+    ("r", Command::Record),
+  ]
+  .into_iter()
+  .map(|(key, command)| (key.to_string(), command))
+  .collect();
+  // the digits play the first nine macros, and with shift their inverses
+  for (index, (key, shifted)) in "123456789".chars().zip("!@#$%^&*(".chars()).enumerate() {
+    keymap.insert(
+      key.to_string(),
+      Command::Macro {
+        index,
+        inverted: false,
+      },
+    );
+    keymap.insert(
+      shifted.to_string(),
+      Command::Macro {
+        index,
+        inverted: true,
+      },
+    );
+  }
+  keymap
+}
+
+impl State {
+  /// Start the effect of a command, e.g. when its key goes down.
+  pub fn press(&mut self, command: &Command) {
+    match *command {
+      Command::Camera { axis, orientation } => {
+        self.camera_axis[axis.index()] = match orientation {
+          Orientation::Positive => 1.0,
+          Orientation::Negative => -1.0,
+        };
+      }
+      Command::Twist { octant } => {
+        self.highlight = None;
+        self.moves.press();
+        self.queue(Twist::Center { octant });
+      }
+      Command::Undo => {
+        self.highlight = None;
+        self.moves.press();
+        self.undo();
+      }
+      Command::Record => {
+        if self.recording.is_some() {
+          self.stop_recording(None);
+        } else {
+          self.start_recording();
+        }
+      }
+      Command::Macro { index, inverted } => {
+        self.highlight = None;
+        self.moves.press();
+        self.play_macro(index, inverted);
+      }
+    }
+  }
+
+  /// Stop the effect of a command, e.g. when its key comes up.
+  pub fn release(&mut self, command: &Command) {
+    if let Command::Camera { axis, .. } = *command {
+      self.camera_axis[axis.index()] = 0.0;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn camera_keys_spin_until_released() {
+    let keymap = default_keymap();
+    let mut state = State::new();
+    state.press(&keymap["d"]);
+    assert_eq!(state.camera_axis, [0., 1., 0.]);
+    state.press(&keymap["w"]);
+    assert_eq!(state.camera_axis, [-1., 1., 0.]);
+    state.release(&keymap["d"]);
+    assert_eq!(state.camera_axis, [-1., 0., 0.]);
+  }
+
+  #[test]
+  fn twist_keys_queue_twists() {
+    let keymap = default_keymap();
+    let mut state = State::new();
+    state.press(&keymap["h"]);
+    state.press(&keymap["y"]);
+    state.release(&keymap["h"]);
+    assert_eq!(
+      state.twist_buffer,
+      [Twist::Center { octant: 7 }, Twist::Center { octant: 3 }]
+    );
+  }
+
+  #[test]
+  fn undo_key_reverses_the_last_twist() {
+    let keymap = default_keymap();
+    let mut state = State::new();
+    state.press(&keymap["h"]);
+    state.press(&keymap["j"]);
+    state.press(&keymap["Backspace"]);
+    assert_eq!(
+      state.twist_buffer,
+      [
+        Twist::Center { octant: 7 },
+        Twist::Center { octant: 5 },
+        Twist::Center { octant: 1 }
+      ]
+    );
+    assert_eq!(state.history, [Twist::Center { octant: 7 }]);
+  }
+}
@@ -0,0 +1,21 @@
+pub mod codec;
+pub mod color;
+pub mod effect;
+pub mod gap;
+pub mod group;
+pub mod input;
+pub mod macros;
+pub mod metric;
+pub mod notation;
+pub mod pattern;
+pub mod permutation;
+pub mod persist;
+pub mod puzzle;
+pub mod render;
+pub mod scramble;
+pub mod share;
+pub mod software;
+pub mod solver;
+pub mod state;
+pub mod terminal;
+pub mod timer;
@@ -0,0 +1,237 @@
+use crate::input::{Command, Keymap};
+use crate::notation;
+use crate::state::{State, Twist};
+
+/// A named move sequence, recorded from play.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Macro {
+  pub name: String,
+  pub twists: Vec<Twist>,
+}
+
+impl State {
+  /// Remember every twist queued from now on, until `stop_recording`.
+  pub fn start_recording(&mut self) {
+    self.recording = Some(Vec::new());
+  }
+
+  /// Keep the recorded twists as a macro, replacing any macro of the same name,
+  /// or naming it after its place in the list when no name is given.
+  /// Returns its index, or nothing when no twists were recorded.
+  pub fn stop_recording(&mut self, name: Option<&str>) -> Option<usize> {
+    let twists = self.recording.take().filter(|twists| !twists.is_empty())?;
+    let name = name.map_or_else(|| (self.macros.len() + 1).to_string(), str::to_string);
+    let recorded = Macro { name, twists };
+    Some(match self.macro_index(&recorded.name) {
+      Some(index) => {
+        self.macros[index] = recorded;
+        index
+      }
+      None => {
+        self.macros.push(recorded);
+        self.macros.len() - 1
+      }
+    })
+  }
+
+  pub fn macro_index(&self, name: &str) -> Option<usize> {
+    self.macros.iter().position(|m| m.name == name)
+  }
+
+  /// Queue the twists of a macro, or undo them when `inverted`.
+  /// Returns false when there is no such macro.
+  pub fn play_macro(&mut self, index: usize, inverted: bool) -> bool {
+    let Some(recorded) = self.macros.get(index) else {
+      return false;
+    };
+    let twists = if inverted {
+      notation::inverse(&recorded.twists)
+    } else {
+      recorded.twists.clone()
+    };
+    for twist in twists {
+      self.queue(twist);
+    }
+    true
+  }
+}
+
+/// Names end up in `save`'s lines, so they can't hold tabs or line breaks.
+pub fn check_name(name: &str) -> Result<(), String> {
+  if name.is_empty() || name.contains(['\t', '\n', '\r']) {
+    return Err(format!("{name:?} is not a macro name"));
+  }
+  Ok(())
+}
+
+/// Macros and the keys bound to them, one per line, for keeping between sessions:
+/// `macro\t<name>\t<moves>` and `key\t<key>\t<name>`, with a trailing `\tinverse`
+/// for keys that play a macro backwards.
+pub fn save(macros: &[Macro], keymap: &Keymap) -> String {
+  let mut lines: Vec<_> = macros
+    .iter()
+    .map(|m| format!("macro\t{}\t{}", m.name, notation::format(&m.twists)))
+    .collect();
+  let mut keys: Vec<_> = keymap
+    .iter()
+    .filter_map(|(key, command)| match *command {
+      Command::Macro { index, inverted } => {
+        let name = &macros.get(index)?.name;
+        Some(format!(
+          "key\t{key}\t{name}{}",
+          if inverted { "\tinverse" } else { "" }
+        ))
+      }
+      _ => None,
+    })
+    .collect();
+  keys.sort();
+  lines.extend(keys);
+  lines.join("\n")
+}
+
+/// Keys and the macro commands they trigger, to add to a keymap.
+pub type Bindings = Vec<(String, Command)>;
+
+/// Read what `save` wrote.
+pub fn load(text: &str) -> Result<(Vec<Macro>, Bindings), String> {
+  let mut macros: Vec<Macro> = Vec::new();
+  let mut bindings = Vec::new();
+  for line in text.lines().filter(|line| !line.is_empty()) {
+    match line.split('\t').collect::<Vec<_>>()[..] {
+      ["macro", name, moves] => {
+        check_name(name)?;
+        macros.push(Macro {
+          name: name.to_string(),
+          twists: notation::parse(moves)?,
+        });
+      }
+      ["key", key, name, ref rest @ ..] if rest.is_empty() || rest == ["inverse"] => {
+        let index = macros
+          .iter()
+          .position(|m| m.name == name)
+          .ok_or_else(|| format!("no macro named {name:?}"))?;
+        let inverted = !rest.is_empty();
+        bindings.push((key.to_string(), Command::Macro { index, inverted }));
+      }
+      _ => return Err(format!("can't read the macro line {line:?}")),
+    }
+  }
+  Ok((macros, bindings))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::input::default_keymap;
+
+  fn twists(moves: &str) -> Vec<Twist> {
+    notation::parse(moves).unwrap()
+  }
+
+  #[test]
+  fn records_and_plays_macros() {
+    let mut state = State::new();
+    state.queue(Twist::ALL[0]);
+    state.start_recording();
+    for twist in twists("J K K K L") {
+      state.queue(twist);
+    }
+    assert!(state.undo());
+    assert_eq!(state.stop_recording(Some("sune")), Some(0));
+    assert_eq!(state.macros[0].twists, twists("J"));
+
+    state.twist_buffer.clear();
+    assert!(state.play_macro(0, true));
+    assert_eq!(state.twist_buffer, twists("J'"));
+    assert!(!state.play_macro(1, false));
+  }
+
+  #[test]
+  fn recording_nothing_keeps_nothing() {
+    let mut state = State::new();
+    state.start_recording();
+    assert_eq!(state.stop_recording(None), None);
+    assert!(state.macros.is_empty());
+    assert_eq!(state.stop_recording(None), None);
+  }
+
+  #[test]
+  fn record_key_fills_the_digit_keys() {
+    let keymap = default_keymap();
+    let mut state = State::new();
+    state.press(&keymap["r"]);
+    state.press(&keymap["h"]);
+    state.press(&keymap["j"]);
+    state.press(&keymap["r"]);
+    assert_eq!(state.macros[0].name, "1");
+    state.twist_buffer.clear();
+    state.press(&keymap["1"]);
+    assert_eq!(state.twist_buffer, twists("H J"));
+    state.twist_buffer.clear();
+    state.press(&keymap["!"]);
+    assert_eq!(state.twist_buffer, twists("J' H'"));
+  }
+
+  #[test]
+  fn same_name_replaces_a_macro() {
+    let mut state = State::new();
+    for moves in ["H", "J"] {
+      state.start_recording();
+      state.queue(twists(moves)[0]);
+      assert_eq!(state.stop_recording(Some("a")), Some(0));
+    }
+    assert_eq!(state.macros.len(), 1);
+    assert_eq!(state.macros[0].twists, twists("J"));
+  }
+
+  #[test]
+  fn save_and_load_round_trip() {
+    let macros = vec![
+      Macro {
+        name: "sexy".to_string(),
+        twists: twists("[H, J]"),
+      },
+      Macro {
+        name: "one".to_string(),
+        twists: twists("K"),
+      },
+    ];
+    let mut keymap = default_keymap();
+    keymap.insert(
+      "m".to_string(),
+      Command::Macro {
+        index: 1,
+        inverted: true,
+      },
+    );
+    let text = save(&macros, &keymap);
+    let (loaded, bindings) = load(&text).unwrap();
+    assert_eq!(loaded, macros);
+    assert!(bindings.contains(&(
+      "m".to_string(),
+      Command::Macro {
+        index: 1,
+        inverted: true
+      }
+    )));
+    assert!(bindings.contains(&(
+      "1".to_string(),
+      Command::Macro {
+        index: 0,
+        inverted: false
+      }
+    )));
+    // the digits past the second macro play nothing and aren't saved
+    assert_eq!(bindings.len(), 5);
+  }
+
+  #[test]
+  fn load_rejects_bad_lines() {
+    assert!(load("macro\ta\tH X").is_err());
+    assert!(load("key\tm\tmissing").is_err());
+    assert!(load("macro\ta\tH\nkey\tm\ta\tbackwards").is_err());
+    assert!(load("something else").is_err());
+    assert_eq!(load("").unwrap().0, []);
+  }
+}
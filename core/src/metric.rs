@@ -0,0 +1,76 @@
+use crate::state::Twist;
+
+/// Moves made, counted the ways solvers count them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MoveCount {
+  /// Every 120 degree twist.
+  pub twists: usize,
+  /// Twists in a row about the same axis count once, so `H H` is one turn.
+  pub turns: usize,
+  /// Key presses that twist, undo or play a macro, however many twists they make.
+  pub presses: usize,
+  last: Option<Twist>,
+}
+
+impl MoveCount {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The twists and turns of a move sequence, which takes no key presses.
+  pub fn of(twists: &[Twist]) -> Self {
+    let mut count = Self::new();
+    for twist in twists {
+      count.twist(*twist);
+    }
+    count
+  }
+
+  pub fn twist(&mut self, twist: Twist) {
+    self.twists += 1;
+    if self
+      .last
+      .is_none_or(|last| last.to_normal() != twist.to_normal())
+    {
+      self.turns += 1;
+    }
+    self.last = Some(twist);
+  }
+
+  pub fn press(&mut self) {
+    self.presses += 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::input::default_keymap;
+  use crate::notation;
+  use crate::state::State;
+
+  #[test]
+  fn turns_merge_twists_about_one_axis() {
+    let count = MoveCount::of(&notation::parse("H H J J' J K H").unwrap());
+    assert_eq!(count.twists, 7);
+    assert_eq!(count.turns, 4);
+    assert_eq!(count.presses, 0);
+  }
+
+  #[test]
+  fn state_counts_presses() {
+    let keymap = default_keymap();
+    let mut state = State::new();
+    state.press(&keymap["r"]);
+    state.press(&keymap["h"]);
+    state.press(&keymap["j"]);
+    state.press(&keymap["r"]);
+    state.press(&keymap["1"]);
+    state.press(&keymap["Backspace"]);
+    state.press(&keymap["w"]);
+    assert_eq!(state.moves.presses, 4);
+    assert_eq!(state.moves.twists, 5);
+    // H J H J J'
+    assert_eq!(state.moves.turns, 4);
+  }
+}
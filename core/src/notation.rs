@@ -0,0 +1,339 @@
+use crate::permutation::PermArray;
+use crate::puzzle::twist_permutations;
+use crate::state::Twist;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::sync::OnceLock;
+
+/// Move letters, named after the keys that perform them.
+/// `H J K L` twist the halves around the white, green, red and silver hexagons
+/// counterclockwise; a trailing `'` turns the other way and a trailing `2` twists twice.
+pub const LETTERS: [char; 4] = ['H', 'J', 'K', 'L'];
+
+/// Parse a move sequence such as `H J' K2 L`.
+/// Letters are case insensitive and whitespace between moves is optional.
+/// `[A, B]` is the commutator `A B A' B'`, `[A: B]` the conjugate `A B A'`,
+/// and `(A)^n` repeats `A` n times, or its inverse for negative n. They nest,
+/// and the brackets and repeats may not expand to more than `MAX_TWISTS` twists.
+pub fn parse(moves: &str) -> Result<Vec<Twist>, String> {
+  let mut chars = moves.chars().peekable();
+  let twists = sequence(&mut chars, false)?;
+  match chars.next() {
+    None => Ok(twists),
+    Some(c) => Err(format!("unexpected `{c}`")),
+  }
+}
+
+// moves up to the end of the input or the bracket that closes this one
+fn sequence(chars: &mut Peekable<Chars>, bracketed: bool) -> Result<Vec<Twist>, String> {
+  let mut twists = Vec::new();
+  // twists written out by brackets and repeats, which can grow far beyond the input
+  let mut expanded = 0;
+  while let Some(&c) = chars.peek() {
+    if c.is_whitespace() {
+      chars.next();
+      continue;
+    }
+    match c {
+      ']' | ')' => break,
+      // separators only belong between the two halves of brackets
+      ',' | ':' if bracketed => break,
+      ',' | ':' => return Err(format!("unexpected `{c}`")),
+      '[' => {
+        chars.next();
+        let a = sequence(chars, true)?;
+        let separator = chars.next();
+        let b = sequence(chars, true)?;
+        match chars.next() {
+          Some(']') => {}
+          Some(c) => return Err(format!("unexpected `{c}`")),
+          None => return Err("unclosed `[`".to_string()),
+        }
+        let commutator = match separator {
+          Some(',') => true,
+          Some(':') => false,
+          _ => return Err("expected `,` or `:` in brackets".to_string()),
+        };
+        let length = 2 * a.len() + b.len() * if commutator { 2 } else { 1 };
+        expand(&mut expanded, length)?;
+        twists.extend(&a);
+        twists.extend(&b);
+        twists.extend(inverse(&a));
+        if commutator {
+          twists.extend(inverse(&b));
+        }
+      }
+      '(' => {
+        chars.next();
+        let group = sequence(chars, false)?;
+        if chars.next() != Some(')') {
+          return Err("unclosed `(`".to_string());
+        }
+        let repeated = power(&group, exponent(chars)?)?;
+        expand(&mut expanded, repeated.len())?;
+        twists.extend(repeated);
+      }
+      _ => {
+        chars.next();
+        let Some(index) = LETTERS.iter().position(|l| l.eq_ignore_ascii_case(&c)) else {
+          return Err(format!("unknown move `{c}`"));
+        };
+        let twist = Twist::ALL[index];
+        match chars.peek() {
+          Some('\'') => {
+            chars.next();
+            twists.push(twist.inverse());
+          }
+          Some('2') => {
+            chars.next();
+            twists.push(twist);
+            twists.push(twist);
+          }
+          _ => twists.push(twist),
+        }
+      }
+    }
+  }
+  Ok(twists)
+}
+
+// count `length` more twists from a bracket or repeat, failing past `MAX_TWISTS`
+fn expand(expanded: &mut usize, length: usize) -> Result<(), String> {
+  *expanded += length;
+  if *expanded > MAX_TWISTS {
+    return Err(format!("the moves expand to more than {MAX_TWISTS} twists"));
+  }
+  Ok(())
+}
+
+// `^n` after parentheses, or once without it
+fn exponent(chars: &mut Peekable<Chars>) -> Result<i64, String> {
+  if chars.peek() != Some(&'^') {
+    return Ok(1);
+  }
+  chars.next();
+  let mut digits = String::new();
+  while let Some(&c) = chars.peek() {
+    if !(c.is_ascii_digit() || c == '-' && digits.is_empty()) {
+      break;
+    }
+    digits.push(c);
+    chars.next();
+  }
+  digits
+    .parse()
+    .map_err(|_| format!("bad exponent `^{digits}`"))
+}
+
+/// Most twists brackets and repeats may expand to, so a short input can't exhaust memory.
+pub const MAX_TWISTS: usize = 10_000;
+
+/// `twists` repeated `exponent` times, or their inverse for a negative exponent,
+/// written out in full. Fails past `MAX_TWISTS` twists.
+pub fn power(twists: &[Twist], exponent: i64) -> Result<Vec<Twist>, String> {
+  let times = exponent.unsigned_abs();
+  if (twists.len() as u64).saturating_mul(times) > MAX_TWISTS as u64 {
+    return Err(format!(
+      "`^{exponent}` expands to more than {MAX_TWISTS} twists"
+    ));
+  }
+  let base = if exponent < 0 {
+    inverse(twists)
+  } else {
+    twists.to_vec()
+  };
+  Ok(base.repeat(times as usize))
+}
+
+/// The name of a single twist, e.g. `J'`.
+pub fn name(twist: Twist) -> String {
+  let index = Twist::ALL.iter().position(|t| *t == twist).unwrap();
+  let mut name = LETTERS[index % 4].to_string();
+  if index >= 4 {
+    name.push('\'');
+  }
+  name
+}
+
+/// Format a move sequence the way `parse` reads it.
+pub fn format(twists: &[Twist]) -> String {
+  twists
+    .iter()
+    .map(|t| name(*t))
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Shorten a move sequence without changing what it does.
+/// Twists about the same axis merge, dropping those that add up to a full turn,
+/// and a twist passes over others it commutes with to reach one it merges with,
+/// e.g. `H J J' H` becomes `H'`.
+pub fn simplify(twists: &[Twist]) -> Vec<Twist> {
+  let mut out = Vec::new();
+  for &twist in twists {
+    append(&mut out, twist);
+  }
+  out
+}
+
+/// Add a twist to a simplified sequence, keeping it simplified.
+pub fn append(twists: &mut Vec<Twist>, twist: Twist) {
+  append_with(twists, twist, &commute);
+}
+
+/// Whether applying two twists in either order gives the same state.
+/// On this puzzle that is only twists about the same axis: no two distinct axes commute,
+/// so `append` never actually merges a twist across another axis for real twists.
+pub fn commute(a: Twist, b: Twist) -> bool {
+  static TABLE: OnceLock<[[bool; 8]; 8]> = OnceLock::new();
+  let table = TABLE.get_or_init(|| {
+    let permutations = Twist::ALL.map(twist_permutations);
+    std::array::from_fn(|i| {
+      std::array::from_fn(|j| {
+        let (a, b) = (&permutations[i], &permutations[j]);
+        PermArray::compose(&a.0, &b.0) == PermArray::compose(&b.0, &a.0)
+          && PermArray::compose(&a.2, &b.2) == PermArray::compose(&b.2, &a.2)
+      })
+    })
+  });
+  let index = |t: Twist| Twist::ALL.iter().position(|a| *a == t).unwrap();
+  table[index(a)][index(b)]
+}
+
+// merges `twist` into the last twist about its axis, across any it commutes with;
+// with the real `commute` that is only ever the last twist, see
+// `twists_merge_across_commuting_ones` for the general case
+fn append_with(twists: &mut Vec<Twist>, twist: Twist, commute: &impl Fn(Twist, Twist) -> bool) {
+  for i in (0..twists.len()).rev() {
+    let other = twists[i];
+    if other.to_normal() == twist.to_normal() {
+      if other == twist {
+        // two thirds of a turn one way are a third the other way
+        twists[i] = twist.inverse();
+      } else {
+        twists.remove(i);
+      }
+      return;
+    }
+    if !commute(other, twist) {
+      break;
+    }
+  }
+  twists.push(twist);
+}
+
+/// The sequence that undoes `twists`.
+pub fn inverse(twists: &[Twist]) -> Vec<Twist> {
+  twists.iter().rev().map(|t| t.inverse()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trip() {
+    let twists = parse("H J' k2 l'").unwrap();
+    assert_eq!(format(&twists), "H J' K K L'");
+    assert_eq!(parse(&format(&twists)).unwrap(), twists);
+  }
+
+  #[test]
+  fn letters_match_keys() {
+    let keymap = crate::input::default_keymap();
+    for (key, letter) in ["h", "j", "k", "l"].into_iter().zip(LETTERS) {
+      let crate::input::Command::Twist { octant } = keymap[key] else {
+        panic!("{key} is not a twist");
+      };
+      assert_eq!(
+        parse(&letter.to_string()).unwrap(),
+        [Twist::Center { octant }]
+      );
+    }
+  }
+
+  #[test]
+  fn rejects_unknown_moves() {
+    assert!(parse("H X").is_err());
+    assert!(parse("[H J]").is_err());
+    assert!(parse("[H, J").is_err());
+    assert!(parse("(H J").is_err());
+    assert!(parse("(H)^").is_err());
+    assert!(parse("H]").is_err());
+  }
+
+  #[test]
+  fn commutators_and_conjugates() {
+    let twists = |moves| parse(moves).unwrap();
+    assert_eq!(twists("[H, J]"), twists("H J H' J'"));
+    assert_eq!(twists("[H: J K]"), twists("H J K H'"));
+    assert_eq!(twists("[H J, K]"), twists("H J K J' H' K'"));
+    assert_eq!(twists("[L: [H, J]]"), twists("L H J H' J' L'"));
+    assert_eq!(twists("(H J)^3"), twists("H J H J H J"));
+    assert_eq!(twists("(H J)^-2"), twists("J' H' J' H'"));
+    // repeats are written out even past the sequence's order
+    assert_eq!(twists("(H J)^7").len(), 14);
+    assert_eq!(twists("(H)^10000").len(), MAX_TWISTS);
+    assert!(parse("(H)^10001").is_err());
+    assert!(parse("(H)^2000000000").is_err());
+    assert!(parse("(H J)^-9223372036854775808").is_err());
+    assert!(parse("(H)^99999999999999999999").is_err());
+    // nesting multiplies the length, so it's counted as it grows
+    let nested = format!("{}H J{}", "(".repeat(14), ")^5".repeat(14));
+    assert!(parse(&nested).unwrap_err().contains("expand"));
+    let nested = format!("{}H, J]{}", "[".repeat(40), ", H]".repeat(39));
+    assert!(parse(&nested).unwrap_err().contains("expand"));
+    assert!(parse(&"(H)^10000 ".repeat(2)).is_err());
+    assert_eq!(parse(&"H ".repeat(20000)).unwrap().len(), 20000);
+    // commas only separate the halves of a commutator
+    assert!(parse("K (H, L)").is_err());
+    assert!(parse("[H, (J, K)]").is_err());
+    assert!(parse("[H, J, K]").is_err());
+    assert!(parse("H, J").is_err());
+    assert_eq!(twists("([H, J])^2 L"), twists("H J H' J' H J H' J' L"));
+  }
+
+  #[test]
+  fn simplifies_neighbouring_twists() {
+    let twists = |moves| parse(moves).unwrap();
+    assert_eq!(simplify(&twists("H H H")), []);
+    assert_eq!(simplify(&twists("H H'")), []);
+    assert_eq!(simplify(&twists("H2")), twists("H'"));
+    assert_eq!(simplify(&twists("H J J' H")), twists("H'"));
+    assert_eq!(simplify(&twists("[H: J]")), twists("H J H'"));
+    assert_eq!(simplify(&twists("[H, H]")), []);
+    assert_eq!(simplify(&twists("[H J K, L]")), twists("[H J K, L]"));
+  }
+
+  #[test]
+  fn twists_about_different_axes_never_commute() {
+    for a in Twist::ALL {
+      for b in Twist::ALL {
+        assert_eq!(commute(a, b), a.to_normal() == b.to_normal(), "{a:?} {b:?}");
+      }
+    }
+  }
+
+  #[test]
+  fn twists_merge_across_commuting_ones() {
+    // pretend H and K commute, as twists about opposite axes would;
+    // no real pair does, see `twists_about_different_axes_never_commute`
+    let twists = |moves| parse(moves).unwrap();
+    let axis = |t: Twist| name(t).replace('\'', "");
+    let commute = |a: Twist, b: Twist| {
+      let mut pair = [axis(a), axis(b)];
+      pair.sort();
+      pair == ["H", "K"] || pair[0] == pair[1]
+    };
+    let simplify = |moves| {
+      let mut out = Vec::new();
+      for twist in twists(moves) {
+        append_with(&mut out, twist, &commute);
+      }
+      out
+    };
+    assert_eq!(simplify("H K H"), twists("H' K"));
+    assert_eq!(simplify("J H K H' J"), twists("J K J"));
+    assert_eq!(simplify("H J K H"), twists("H J K H"));
+  }
+}
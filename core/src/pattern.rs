@@ -0,0 +1,75 @@
+use crate::puzzle::{Face, Hex, Square};
+
+// The atlas is a grid of square tiles, one per color, each of which repeats seamlessly.
+pub const TILE_SIZE: usize = 32;
+pub const ATLAS_COLUMNS: usize = 4;
+pub const ATLAS_ROWS: usize = 2;
+pub const ATLAS_WIDTH: usize = TILE_SIZE * ATLAS_COLUMNS;
+pub const ATLAS_HEIGHT: usize = TILE_SIZE * ATLAS_ROWS;
+
+/// Index of the atlas tile drawn over a face.
+/// Patterns follow the factory color names,
+/// so a square and a hexagon of the same color share a pattern.
+pub fn pattern(face: Face) -> u8 {
+  let hex = match face {
+    Face::Hex(hex) => hex,
+    Face::Square(square) => match square {
+      Square::White => Hex::White,
+      Square::Yellow => Hex::Yellow,
+      Square::Blue => Hex::Blue,
+      Square::Green => Hex::Green,
+      Square::Red => Hex::Red,
+      Square::Orange => Hex::Orange,
+    },
+  };
+  hex as u8
+}
+
+/// Coverage of a pattern at a pixel of its tile.
+/// Facets are textured in arbitrary orientations,
+/// so the patterns are chosen to be recognizable when rotated.
+fn coverage(pattern: u8, x: usize, y: usize) -> bool {
+  // distance squared from the center of a cell of the given size
+  let dist = |cell: usize| {
+    let dx = (x % cell) as i32 * 2 + 1 - cell as i32;
+    let dy = (y % cell) as i32 * 2 + 1 - cell as i32;
+    (dx * dx + dy * dy) / 4
+  };
+  match Hex::ALL[pattern as usize] {
+    // plain
+    Hex::White => false,
+    // small dots
+    Hex::Pink => dist(8) <= 2,
+    // stripes
+    Hex::Red => (x + y) % 8 < 3,
+    // checkerboard
+    Hex::Blue => (x / 8 + y / 8).is_multiple_of(2),
+    // large dots
+    Hex::Yellow => dist(16) <= 20,
+    // grid
+    Hex::Silver => x % 16 < 2 || y % 16 < 2,
+    // rings
+    Hex::Orange => (16..=42).contains(&dist(16)),
+    // crosses
+    Hex::Green => {
+      let (cx, cy) = (x % 16, y % 16);
+      let bar = |a: usize, b: usize| (6..10).contains(&a) && (2..14).contains(&b);
+      bar(cx, cy) || bar(cy, cx)
+    }
+  }
+}
+
+/// Rgba pixels of the pattern atlas, rows from top to bottom.
+/// Patterns are stored in the alpha channel.
+pub fn atlas() -> Vec<u8> {
+  let mut pixels = vec![0; 4 * ATLAS_WIDTH * ATLAS_HEIGHT];
+  for y in 0..ATLAS_HEIGHT {
+    for x in 0..ATLAS_WIDTH {
+      let pattern = (y / TILE_SIZE) * ATLAS_COLUMNS + x / TILE_SIZE;
+      let covered = coverage(pattern as u8, x % TILE_SIZE, y % TILE_SIZE);
+      let i = 4 * (y * ATLAS_WIDTH + x);
+      pixels[i..i + 4].copy_from_slice(&[255, 255, 255, if covered { 255 } else { 0 }]);
+    }
+  }
+  pixels
+}
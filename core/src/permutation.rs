@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PermHashMap(HashMap<u8, u8>);
+
+impl PermHashMap {
+  pub fn identity() -> Self {
+    Self(HashMap::default())
+  }
+
+  pub fn invert(&self) -> Self {
+    Self(self.0.iter().map(|(k, v)| (*v, *k)).collect())
+  }
+
+  pub fn compose(p: &Self, q: &Self) -> Self {
+    let (mut p, q) = p
+      .0
+      .iter()
+      .fold((p.clone(), q.clone()), |(mut p, mut q), (k, v)| {
+        let qv = q.permute(*v);
+        if *k == qv {
+          p.0.remove(k);
+        } else {
+          p.0.insert(*k, qv);
+        }
+        q.0.remove(v);
+        (p, q)
+      });
+    for (key, value) in q.0 {
+      p.0.insert(key, value);
+    }
+    p
+  }
+
+  pub fn permute(&self, k: u8) -> u8 {
+    self.0.get(&k).map_or(k, |v| *v)
+  }
+
+  /// The cycles of length two or more, each starting at its smallest element.
+  pub fn cycles(&self) -> Vec<Vec<u8>> {
+    let mut starts: Vec<_> = self.0.keys().copied().collect();
+    starts.sort();
+    let mut seen = std::collections::HashSet::new();
+    let mut cycles = Vec::new();
+    for start in starts {
+      if seen.contains(&start) {
+        continue;
+      }
+      let mut cycle = Vec::new();
+      let mut k = start;
+      while seen.insert(k) {
+        cycle.push(k);
+        k = self.permute(k);
+      }
+      cycles.push(cycle);
+    }
+    cycles
+  }
+
+  /// The permutation sending each element of a cycle to the next one.
+  pub fn from_cycles(cycles: &[Vec<u8>]) -> Result<Self, String> {
+    check_disjoint(cycles, usize::MAX)?;
+    Ok(cycle_pairs(cycles).collect())
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PermArray<const N: usize>([u8; N]);
+//Assert<{ N < 256 }>: IsTrue;
+impl<const N: usize> PermArray<N> {
+  pub fn identity() -> Self {
+    let mut array = [0; N];
+    for (i, v) in array.iter_mut().enumerate() {
+      *v = i as u8;
+    }
+    Self(array)
+  }
+
+  pub fn invert(&self) -> Self {
+    let mut array = [0; N];
+    for (k, v) in self.0.iter().enumerate() {
+      array[*v as usize] = k as u8;
+    }
+    Self(array)
+  }
+
+  /// Apply p, then q.
+  pub fn compose(p: &Self, q: &Self) -> Self {
+    let mut array = [0; N];
+    for (k, v) in p.0.iter().enumerate() {
+      array[k] = q.permute(*v);
+    }
+    Self(array)
+  }
+
+  pub fn permute(&self, k: u8) -> u8 {
+    self.0[k as usize]
+  }
+
+  pub fn as_array(&self) -> &[u8; N] {
+    &self.0
+  }
+
+  /// The cycles of length two or more, each starting at its smallest element.
+  pub fn cycles(&self) -> Vec<Vec<u8>> {
+    let mut seen = [false; N];
+    let mut cycles = Vec::new();
+    for start in 0..N {
+      if seen[start] || self.0[start] as usize == start {
+        continue;
+      }
+      let mut cycle = Vec::new();
+      let mut k = start;
+      while !seen[k] {
+        seen[k] = true;
+        cycle.push(k as u8);
+        k = self.0[k] as usize;
+      }
+      cycles.push(cycle);
+    }
+    cycles
+  }
+
+  /// The permutation sending each element of a cycle to the next one.
+  pub fn from_cycles(cycles: &[Vec<u8>]) -> Result<Self, String> {
+    check_disjoint(cycles, N)?;
+    let mut array = *Self::identity().as_array();
+    for (k, v) in cycle_pairs(cycles) {
+      array[k as usize] = v;
+    }
+    Ok(Self(array))
+  }
+
+  /// Whether it is a product of an even number of transpositions.
+  pub fn is_even(&self) -> bool {
+    self
+      .cycles()
+      .iter()
+      .map(|cycle| cycle.len() - 1)
+      .sum::<usize>()
+      .is_multiple_of(2)
+  }
+
+  /// Index among all permutations of N elements in lexicographic order, from the Lehmer code.
+  /// N! has to fit, so N is at most 34.
+  pub fn rank(&self) -> u128 {
+    (0..N).fold(0, |rank, i| {
+      let smaller = self.0[i + 1..].iter().filter(|k| **k < self.0[i]).count();
+      rank * (N - i) as u128 + smaller as u128
+    })
+  }
+
+  /// The permutation with a given `rank`, if it is below N!.
+  pub fn from_rank(mut rank: u128) -> Option<Self> {
+    let mut code = [0; N];
+    for i in (0..N).rev() {
+      let radix = (N - i) as u128;
+      code[i] = (rank % radix) as usize;
+      rank /= radix;
+    }
+    if rank != 0 {
+      return None;
+    }
+    let mut left: Vec<u8> = (0..N as u8).collect();
+    Some(Self(code.map(|smaller| left.remove(smaller))))
+  }
+
+  /// Index among the even permutations, which are half of them.
+  /// Ranks 2k and 2k + 1 differ by swapping the last two elements, so one of them is even.
+  pub fn rank_even(&self) -> Option<u128> {
+    self.is_even().then(|| self.rank() / 2)
+  }
+
+  /// The even permutation with a given `rank_even`, if it is below N!/2.
+  pub fn from_rank_even(rank: u128) -> Option<Self> {
+    let p = Self::from_rank(rank.checked_mul(2)?)?;
+    if p.is_even() {
+      Some(p)
+    } else {
+      Self::from_rank(rank * 2 + 1)
+    }
+  }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for PermArray<N> {
+  type Error = String;
+
+  /// Checks that every element from 0 to N - 1 appears exactly once.
+  fn try_from(slice: &[u8]) -> Result<Self, String> {
+    let array: [u8; N] = slice
+      .try_into()
+      .map_err(|_| format!("expected {N} elements, got {}", slice.len()))?;
+    let mut seen = [false; N];
+    for k in array {
+      match seen.get_mut(k as usize) {
+        Some(seen) if !*seen => *seen = true,
+        _ => return Err(format!("{array:?} is not a permutation")),
+      }
+    }
+    Ok(Self(array))
+  }
+}
+
+impl<const N: usize> From<[u8; N]> for PermArray<N> {
+  fn from(array: [u8; N]) -> Self {
+    Self(array)
+  }
+}
+
+impl FromIterator<(u8, u8)> for PermHashMap {
+  fn from_iter<I: IntoIterator<Item = (u8, u8)>>(iter: I) -> Self {
+    Self(iter.into_iter().filter(|(k, v)| k != v).collect())
+  }
+}
+
+/// Disjoint cycle notation, e.g. `(0 5 3)(2 7)`, with `()` for the identity.
+impl fmt::Display for PermHashMap {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&format_cycles(&self.cycles(), |k| k.to_string()))
+  }
+}
+
+impl<const N: usize> fmt::Display for PermArray<N> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(&format_cycles(&self.cycles(), |k| k.to_string()))
+  }
+}
+
+impl FromStr for PermHashMap {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, String> {
+    Self::from_cycles(&parse_cycles(s, |name| name.parse().ok())?)
+  }
+}
+
+impl<const N: usize> FromStr for PermArray<N> {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, String> {
+    Self::from_cycles(&parse_cycles(s, |name| name.parse().ok())?)
+  }
+}
+
+/// Write cycles as `(a b c)(d e)`, naming each element with `label`.
+pub fn format_cycles(cycles: &[Vec<u8>], label: impl Fn(u8) -> String) -> String {
+  if cycles.is_empty() {
+    return "()".to_string();
+  }
+  cycles
+    .iter()
+    .map(|cycle| {
+      let names: Vec<_> = cycle.iter().map(|k| label(*k)).collect();
+      format!("({})", names.join(" "))
+    })
+    .collect()
+}
+
+/// Read cycles written by `format_cycles`, or with commas between the elements as GAP writes them.
+/// `lookup` turns each name back into its element.
+pub fn parse_cycles(
+  text: &str,
+  lookup: impl Fn(&str) -> Option<u8>,
+) -> Result<Vec<Vec<u8>>, String> {
+  let mut cycles = Vec::new();
+  let mut rest = text.trim();
+  while !rest.is_empty() {
+    let Some(inner) = rest.strip_prefix('(') else {
+      return Err(format!("expected `(` at `{rest}`"));
+    };
+    let Some(end) = inner.find(')') else {
+      return Err(format!("unclosed cycle `{rest}`"));
+    };
+    let cycle = inner[..end]
+      .split(|c: char| c.is_whitespace() || c == ',')
+      .filter(|name| !name.is_empty())
+      .map(|name| lookup(name).ok_or_else(|| format!("unknown element `{name}`")))
+      .collect::<Result<Vec<_>, _>>()?;
+    if cycle.len() > 1 {
+      cycles.push(cycle);
+    }
+    rest = inner[end + 1..].trim_start();
+  }
+  Ok(cycles)
+}
+
+fn check_disjoint(cycles: &[Vec<u8>], n: usize) -> Result<(), String> {
+  let mut seen = std::collections::HashSet::new();
+  for &k in cycles.iter().flatten() {
+    if k as usize >= n {
+      return Err(format!("{k} is out of range for {n} elements"));
+    }
+    if !seen.insert(k) {
+      return Err(format!("{k} appears more than once"));
+    }
+  }
+  Ok(())
+}
+
+fn cycle_pairs(cycles: &[Vec<u8>]) -> impl Iterator<Item = (u8, u8)> + '_ {
+  cycles.iter().flat_map(|cycle| {
+    let next = cycle.iter().cycle().skip(1);
+    cycle.iter().copied().zip(next.copied())
+  })
+}
+
+/// What the stabilizer chain needs from a permutation of the points 0 to 255.
+pub trait Permutation: Clone + PartialEq {
+  fn identity() -> Self;
+  fn invert(&self) -> Self;
+  /// Apply p, then q.
+  fn compose(p: &Self, q: &Self) -> Self;
+  fn permute(&self, k: u8) -> u8;
+  /// The smallest point that doesn't stay put.
+  fn first_moved(&self) -> Option<u8>;
+}
+
+impl Permutation for PermHashMap {
+  fn identity() -> Self {
+    PermHashMap::identity()
+  }
+
+  fn invert(&self) -> Self {
+    PermHashMap::invert(self)
+  }
+
+  fn compose(p: &Self, q: &Self) -> Self {
+    PermHashMap::compose(p, q)
+  }
+
+  fn permute(&self, k: u8) -> u8 {
+    PermHashMap::permute(self, k)
+  }
+
+  fn first_moved(&self) -> Option<u8> {
+    self.0.keys().copied().min()
+  }
+}
+
+impl<const N: usize> Permutation for PermArray<N> {
+  fn identity() -> Self {
+    PermArray::identity()
+  }
+
+  fn invert(&self) -> Self {
+    PermArray::invert(self)
+  }
+
+  fn compose(p: &Self, q: &Self) -> Self {
+    PermArray::compose(p, q)
+  }
+
+  fn permute(&self, k: u8) -> u8 {
+    PermArray::permute(self, k)
+  }
+
+  fn first_moved(&self) -> Option<u8> {
+    (0..N as u8).find(|k| self.permute(*k) != *k)
+  }
+}
+
+/// The group generated by some permutations, as a chain of stabilizers
+/// built with the Schreier-Sims algorithm.
+/// Level i holds the elements fixing the first i base points,
+/// and for each point the next base point can go to, one element that takes it there.
+/// Every group element is then one such element per level, composed,
+/// which gives the group order, membership tests and uniform sampling.
+pub struct StabilizerChain<P> {
+  levels: Vec<Level<P>>,
+}
+
+struct Level<P> {
+  base: u8,
+  generators: Vec<P>,
+  orbit: Vec<u8>,
+  // an element taking the base point to each point of the orbit
+  transversal: HashMap<u8, P>,
+}
+
+impl<P: Permutation> Level<P> {
+  fn new(base: u8) -> Self {
+    Self {
+      base,
+      generators: vec![],
+      orbit: vec![base],
+      transversal: HashMap::from([(base, P::identity())]),
+    }
+  }
+
+  fn grow_orbit(&mut self) {
+    let mut i = 0;
+    while i < self.orbit.len() {
+      let u = self.orbit[i];
+      for s in &self.generators {
+        let v = s.permute(u);
+        if !self.transversal.contains_key(&v) {
+          let t = P::compose(&self.transversal[&u], s);
+          self.transversal.insert(v, t);
+          self.orbit.push(v);
+        }
+      }
+      i += 1;
+    }
+  }
+}
+
+impl<P: Permutation> StabilizerChain<P> {
+  pub fn new(generators: &[P]) -> Self {
+    let mut chain = Self { levels: vec![] };
+    for g in generators {
+      chain.insert(0, g.clone());
+    }
+    chain
+  }
+
+  /// Number of elements in the group.
+  pub fn order(&self) -> u128 {
+    self.levels.iter().map(|l| l.orbit.len() as u128).product()
+  }
+
+  /// The points whose images pin down an element.
+  pub fn base(&self) -> Vec<u8> {
+    self.levels.iter().map(|l| l.base).collect()
+  }
+
+  pub fn contains(&self, g: &P) -> bool {
+    self.sift(0, g.clone()) == P::identity()
+  }
+
+  /// A uniformly random element.
+  /// `below(n)` should return a uniformly random number less than n.
+  pub fn random(&self, mut below: impl FnMut(usize) -> usize) -> P {
+    // deepest level first, as sift takes them off last
+    self.levels.iter().rev().fold(P::identity(), |g, level| {
+      let u = level.orbit[below(level.orbit.len())];
+      P::compose(&g, &level.transversal[&u])
+    })
+  }
+
+  // divide out the levels from `level` down; the identity is left for members
+  fn sift(&self, level: usize, mut g: P) -> P {
+    for level in &self.levels[level..] {
+      let Some(t) = level.transversal.get(&g.permute(level.base)) else {
+        break;
+      };
+      g = P::compose(&g, &t.invert());
+    }
+    g
+  }
+
+  fn insert(&mut self, level: usize, g: P) {
+    if self.sift(level, g.clone()) == P::identity() {
+      return;
+    }
+    if level == self.levels.len() {
+      self.levels.push(Level::new(g.first_moved().unwrap()));
+    }
+    self.levels[level].generators.push(g);
+    self.levels[level].grow_orbit();
+
+    // Schreier generators, which generate the stabilizer of the base point
+    let current = &self.levels[level];
+    let mut schreier = Vec::new();
+    for u in &current.orbit {
+      let t = &current.transversal[u];
+      for s in &current.generators {
+        let back = current.transversal[&s.permute(*u)].invert();
+        schreier.push(P::compose(&P::compose(t, s), &back));
+      }
+    }
+    for h in schreier {
+      self.insert(level + 1, h);
+    }
+  }
+}
+
+// For const assertions
+pub enum Assert<const CHECK: bool> {}
+pub trait IsTrue {}
+impl IsTrue for Assert<true> {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn array_inverse_composes_to_identity() {
+    let p = PermArray::from([2, 0, 1, 4, 3]);
+    assert_eq!(PermArray::compose(&p, &p.invert()), PermArray::identity());
+    assert_eq!(PermArray::compose(&p.invert(), &p), PermArray::identity());
+  }
+
+  #[test]
+  fn array_compose_applies_left_first() {
+    let p = PermArray::from([1, 2, 0]);
+    let q = PermArray::from([0, 2, 1]);
+    let pq = PermArray::compose(&p, &q);
+    for k in 0..3 {
+      assert_eq!(pq.permute(k), q.permute(p.permute(k)));
+    }
+  }
+
+  #[test]
+  fn array_from_slice_checks_elements() {
+    assert_eq!(
+      PermArray::<3>::try_from(&[2, 0, 1][..]),
+      Ok(PermArray::from([2, 0, 1]))
+    );
+    assert!(PermArray::<3>::try_from(&[0, 0, 1][..]).is_err());
+    assert!(PermArray::<3>::try_from(&[0, 1, 3][..]).is_err());
+    assert!(PermArray::<3>::try_from(&[0, 1][..]).is_err());
+  }
+
+  #[test]
+  fn array_cycles() {
+    let p = PermArray::from([2, 1, 3, 0, 5, 4]);
+    assert_eq!(p.cycles(), vec![vec![0, 2, 3], vec![4, 5]]);
+    assert!(PermArray::<4>::identity().cycles().is_empty());
+  }
+
+  #[test]
+  fn cycle_notation_round_trip() {
+    let p = PermArray::from([5, 1, 7, 0, 4, 3, 6, 2]);
+    assert_eq!(p.to_string(), "(0 5 3)(2 7)");
+    assert_eq!("(0 5 3)(2 7)".parse(), Ok(p.clone()));
+    assert_eq!("(0,5,3) (2,7)".parse(), Ok(p));
+    assert_eq!(PermArray::<4>::identity().to_string(), "()");
+    assert_eq!("()".parse(), Ok(PermArray::<4>::identity()));
+
+    let q: PermHashMap = "(0 5 3)(2 7)".parse().unwrap();
+    assert_eq!(q.to_string(), "(0 5 3)(2 7)");
+    assert_eq!(q.permute(3), 0);
+  }
+
+  #[test]
+  fn cycle_notation_rejects_bad_input() {
+    assert!("(0 1)(1 2)".parse::<PermArray<3>>().is_err());
+    assert!("(0 3)".parse::<PermArray<3>>().is_err());
+    assert!("(0 1".parse::<PermArray<3>>().is_err());
+    assert!("0 1".parse::<PermArray<3>>().is_err());
+    assert!("(0 x)".parse::<PermHashMap>().is_err());
+  }
+
+  #[test]
+  fn chain_order_and_membership() {
+    let cycle = PermArray::from([1, 2, 3, 4, 0]);
+    let swap = PermArray::from([1, 0, 2, 3, 4]);
+    let symmetric = StabilizerChain::new(&[cycle.clone(), swap.clone()]);
+    assert_eq!(symmetric.order(), 120);
+    assert!(symmetric.contains(&swap));
+
+    let three_cycles = [
+      PermArray::from([1, 2, 0, 3, 4]),
+      PermArray::from([0, 1, 3, 4, 2]),
+    ];
+    let alternating = StabilizerChain::new(&three_cycles);
+    assert_eq!(alternating.order(), 60);
+    assert!(alternating.contains(&PermArray::compose(
+      &swap,
+      &PermArray::from([0, 1, 3, 2, 4])
+    )));
+    assert!(!alternating.contains(&swap));
+
+    assert_eq!(StabilizerChain::new(&[cycle]).order(), 5);
+    assert_eq!(StabilizerChain::<PermArray<5>>::new(&[]).order(), 1);
+  }
+
+  #[test]
+  fn chain_on_hash_maps() {
+    let cycle: PermHashMap = [(10, 20), (20, 30), (30, 10)].into_iter().collect();
+    let swap: PermHashMap = [(30, 40), (40, 30)].into_iter().collect();
+    let chain = StabilizerChain::new(&[cycle, swap.clone()]);
+    assert_eq!(chain.order(), 24);
+    assert!(chain.contains(&swap));
+    assert!(!chain.contains(&[(10, 50), (50, 10)].into_iter().collect()));
+  }
+
+  #[test]
+  fn random_elements_are_uniform() {
+    let chain = StabilizerChain::new(&[PermArray::from([1, 2, 0]), PermArray::from([1, 0, 2])]);
+    let mut seed = 1_u64;
+    let mut below = |n: usize| {
+      seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+      ((seed >> 33) % n as u64) as usize
+    };
+    let mut counts = HashMap::new();
+    for _ in 0..6000 {
+      let g = chain.random(&mut below);
+      assert!(chain.contains(&g));
+      *counts.entry(g).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), 6);
+    assert!(
+      counts.values().all(|c| (800..1200).contains(c)),
+      "{counts:?}"
+    );
+  }
+
+  #[test]
+  fn hash_map_matches_array() {
+    let p: PermHashMap = [(0, 1), (1, 2), (2, 0)].into_iter().collect();
+    let q: PermHashMap = [(2, 3), (3, 2)].into_iter().collect();
+    let pq = PermHashMap::compose(&p, &q);
+    for k in 0..5 {
+      assert_eq!(pq.permute(k), q.permute(p.permute(k)));
+    }
+    assert_eq!(
+      PermHashMap::compose(&p, &p.invert()),
+      PermHashMap::identity()
+    );
+  }
+
+  #[test]
+  fn ranks_follow_lexicographic_order() {
+    let mut last = None;
+    for rank in 0..120 {
+      let p = PermArray::<5>::from_rank(rank).unwrap();
+      assert_eq!(p.rank(), rank);
+      assert!(last.is_none_or(|last: PermArray<5>| last.as_array() < p.as_array()));
+      last = Some(p);
+    }
+    assert_eq!(PermArray::<5>::from_rank(0), Some(PermArray::identity()));
+    assert_eq!(last, Some(PermArray::from([4, 3, 2, 1, 0])));
+    assert_eq!(PermArray::<5>::from_rank(120), None);
+    let big = PermArray::<24>::from_rank(123_456_789_012_345_678).unwrap();
+    assert_eq!(big.rank(), 123_456_789_012_345_678);
+  }
+
+  #[test]
+  fn even_ranks_cover_the_even_permutations() {
+    let evens: Vec<_> = (0..12)
+      .map(|rank| PermArray::<4>::from_rank_even(rank).unwrap())
+      .collect();
+    for (rank, p) in evens.iter().enumerate() {
+      assert!(p.is_even());
+      assert_eq!(p.rank_even(), Some(rank as u128));
+    }
+    let distinct: std::collections::HashSet<_> = evens.iter().collect();
+    assert_eq!(distinct.len(), 12);
+    assert_eq!(PermArray::<4>::from_rank_even(12), None);
+    assert_eq!(PermArray::from([1, 0, 2, 3]).rank_even(), None);
+  }
+}
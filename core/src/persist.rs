@@ -0,0 +1,286 @@
+use crate::codec;
+use crate::color::{Color, ColorScheme};
+use crate::notation;
+use crate::puzzle::Puzzle;
+use crate::solver::Solver;
+use crate::state::{State, Twist};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Schema of saved fields. Bump it when a field changes meaning,
+/// and teach `Fields::parse` or `State::restore` to read the older versions.
+/// Version 2 saves the puzzle as its `codec` rank instead of all 32 bytes of `Puzzle::to_bytes`.
+pub const VERSION: u32 = 2;
+
+/// Saved values by name, written as a `version` line and then one `name value` line each.
+/// Fields a build doesn't know are kept but ignored, so adding one needs no new version.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fields(BTreeMap<String, String>);
+
+impl Fields {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn set(&mut self, name: &str, value: impl ToString) {
+    self.0.insert(name.to_string(), value.to_string());
+  }
+
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.0.get(name).map(String::as_str)
+  }
+
+  /// Read what `to_string` wrote, refusing saves from newer builds.
+  pub fn parse(text: &str) -> Result<Self, String> {
+    let mut lines = text.lines();
+    let version = lines
+      .next()
+      .and_then(|line| line.strip_prefix("version "))
+      .and_then(|v| v.parse::<u32>().ok())
+      .ok_or("the save has no version")?;
+    if version > VERSION {
+      return Err(format!(
+        "the save is version {version}, newer than this build's {VERSION}"
+      ));
+    }
+    let mut fields = Self::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+      let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+      fields.set(name, value);
+    }
+    Ok(fields)
+  }
+}
+
+impl fmt::Display for Fields {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "version {VERSION}")?;
+    for (name, value) in &self.0 {
+      writeln!(f, "{name} {value}")?;
+    }
+    Ok(())
+  }
+}
+
+impl State {
+  /// The puzzle as it will be once queued twists are done, the camera,
+  /// the undo history and the color scheme, by name and by its colors
+  /// so schemes added at runtime come back too.
+  pub fn save(&self, fields: &mut Fields) {
+    let bytes = codec::encode(&self.target()).expect("twists only reach solvable states");
+    fields.set(
+      "puzzle",
+      bytes.iter().map(|b| format!("{b:02x}")).collect::<String>(),
+    );
+    let camera: Vec<_> = self.camera_transform.iter().map(f32::to_string).collect();
+    fields.set("camera", camera.join(" "));
+    fields.set("history", notation::format(&self.history));
+    fields.set("scheme", &self.color_scheme.name);
+    let scheme = &self.color_scheme;
+    let colors: Vec<_> = scheme
+      .squares
+      .iter()
+      .chain(&scheme.hexes)
+      .map(Color::to_hex)
+      .collect();
+    fields.set("colors", colors.join(" "));
+  }
+
+  /// Put back what `save` kept. Saves from before the scheme's colors were kept
+  /// leave the scheme alone, for the caller to look up by name.
+  /// The pieces are moved into place by twisting a solved puzzle along a solution backwards.
+  /// Missing fields are left as they are; on error nothing changes.
+  pub fn restore(&mut self, fields: &Fields, solver: &Solver) -> Result<(), String> {
+    let puzzle = match fields.get("puzzle") {
+      Some(hex) => {
+        let bytes = (0..hex.len())
+          .step_by(2)
+          .map(|i| {
+            hex
+              .get(i..i + 2)
+              .and_then(|b| u8::from_str_radix(b, 16).ok())
+              .ok_or_else(|| format!("bad puzzle bytes `{hex}`"))
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        // version 1 saved every byte of the puzzle
+        Some(if bytes.len() == codec::BYTES {
+          codec::decode(&bytes)?
+        } else {
+          Puzzle::from_bytes(&bytes)?
+        })
+      }
+      None => None,
+    };
+    let scramble = puzzle
+      .map(|puzzle| scramble_for(&puzzle, solver))
+      .transpose()?;
+    let camera = match fields.get("camera") {
+      Some(camera) => {
+        let values = camera
+          .split(' ')
+          .map(|v| {
+            v.parse::<f32>()
+              .map_err(|_| format!("bad camera `{camera}`"))
+          })
+          .collect::<Result<Vec<_>, _>>()?;
+        Some(<[f32; 16]>::try_from(values).map_err(|_| format!("bad camera `{camera}`"))?)
+      }
+      None => None,
+    };
+    let history = fields.get("history").map(notation::parse).transpose()?;
+    let scheme = match (fields.get("scheme"), fields.get("colors")) {
+      (Some(name), Some(colors)) => {
+        let colors: Vec<_> = colors.split(' ').map(str::to_string).collect();
+        let (squares, hexes) = colors.split_at(colors.len().min(6));
+        Some(ColorScheme::from_hex(name.to_string(), squares, hexes)?)
+      }
+      _ => None,
+    };
+
+    if let Some(scramble) = scramble {
+      self.replay(scramble);
+    }
+    if let Some(camera) = camera {
+      self.camera_transform = camera;
+    }
+    if let Some(history) = history {
+      self.history = history;
+    }
+    if let Some(scheme) = scheme {
+      self.color_scheme = scheme;
+    }
+    Ok(())
+  }
+
+  /// Reset to a given position, as a link or a save describes it.
+  /// On error nothing changes.
+  pub fn arrange(&mut self, puzzle: &Puzzle, solver: &Solver) -> Result<(), String> {
+    let scramble = scramble_for(puzzle, solver)?;
+    self.replay(scramble);
+    Ok(())
+  }
+
+  fn replay(&mut self, scramble: Vec<Twist>) {
+    self.reset();
+    self.twist_buffer.extend(scramble);
+    self.finish_twists();
+  }
+}
+
+// twists from solved to `puzzle`: a solution, backwards
+fn scramble_for(puzzle: &Puzzle, solver: &Solver) -> Result<Vec<Twist>, String> {
+  let mut search = solver.start(puzzle)?;
+  loop {
+    if let Some(solution) = search.step(solver) {
+      return Ok(notation::inverse(&solution));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fields_round_trip() {
+    let mut fields = Fields::new();
+    fields.set("patterns", true);
+    fields.set("background", "gradient #000000 #ffffff");
+    fields.set("empty", "");
+    let text = fields.to_string();
+    assert!(text.starts_with(&format!("version {VERSION}\n")));
+    assert_eq!(Fields::parse(&text), Ok(fields));
+  }
+
+  #[test]
+  fn refuses_newer_and_unversioned_saves() {
+    assert!(Fields::parse(&format!("version {}\npatterns true", VERSION + 1)).is_err());
+    assert!(Fields::parse("patterns true").is_err());
+    assert!(Fields::parse("").is_err());
+    assert_eq!(Fields::parse("version 0\n").unwrap(), Fields::new());
+  }
+
+  #[test]
+  fn state_round_trip() {
+    let solver = Solver::new();
+    let mut state = State::new();
+    for twist in notation::parse("H J' K L H").unwrap() {
+      state.queue(twist);
+    }
+    state.advance(1.);
+    state.camera_transform[1] = 0.5;
+    state.color_scheme = ColorScheme::from_hex(
+      "mine".to_string(),
+      &vec!["#010203".to_string(); 6],
+      &vec!["#fefdfc".to_string(); 8],
+    )
+    .unwrap();
+    let mut fields = Fields::new();
+    state.save(&mut fields);
+    let fields = Fields::parse(&fields.to_string()).unwrap();
+
+    let mut restored = State::new();
+    restored.restore(&fields, &solver).unwrap();
+    assert_eq!(restored.puzzle.to_bytes(), state.target().to_bytes());
+    assert_eq!(restored.history, state.history);
+    assert_eq!(restored.camera_transform, state.camera_transform);
+    assert_eq!(restored.color_scheme, state.color_scheme);
+    state.finish_twists();
+    let positions = state.get_vertex_positions();
+    for (a, b) in restored.get_vertex_positions().iter().zip(&positions) {
+      assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+  }
+
+  #[test]
+  fn reads_version_one_puzzles() {
+    let solver = Solver::new();
+    let mut puzzle = Puzzle::new();
+    for twist in notation::parse("H K'").unwrap() {
+      puzzle.twist(twist);
+    }
+    let hex: String = puzzle
+      .to_bytes()
+      .iter()
+      .map(|b| format!("{b:02x}"))
+      .collect();
+    let fields = Fields::parse(&format!("version 1\npuzzle {hex}\n")).unwrap();
+    let mut state = State::new();
+    state.restore(&fields, &solver).unwrap();
+    assert_eq!(state.puzzle.to_bytes(), puzzle.to_bytes());
+  }
+
+  #[test]
+  fn arranges_a_linked_puzzle() {
+    let solver = Solver::new();
+    let mut puzzle = Puzzle::new();
+    for twist in notation::parse("K L' H").unwrap() {
+      puzzle.twist(twist);
+    }
+    let mut state = State::new();
+    state.queue(Twist::ALL[0]);
+    state.arrange(&puzzle, &solver).unwrap();
+    assert!(state.twist_buffer.is_empty());
+    assert_eq!(state.puzzle.to_bytes(), puzzle.to_bytes());
+  }
+
+  #[test]
+  fn bad_saves_change_nothing() {
+    let solver = Solver::new();
+    let mut state = State::new();
+    state.queue(Twist::ALL[0]);
+    let mut fields = Fields::new();
+    fields.set("puzzle", "00ff");
+    assert!(state.restore(&fields, &solver).is_err());
+    fields.set("puzzle", "zz");
+    assert!(state.restore(&fields, &solver).is_err());
+    let mut fields = Fields::new();
+    fields.set("camera", "1 2 3");
+    assert!(state.restore(&fields, &solver).is_err());
+    let mut fields = Fields::new();
+    fields.set("scheme", "mine");
+    fields.set("colors", "#000000 #ffffff");
+    assert!(state.restore(&fields, &solver).is_err());
+    assert_eq!(state.twist_buffer.len(), 1);
+  }
+}
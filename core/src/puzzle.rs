@@ -0,0 +1,422 @@
+use crate::permutation::PermArray;
+use crate::state::Twist;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hex {
+  White,
+  Pink,
+  Red,
+  Blue,
+  Yellow,
+  Silver,
+  Orange,
+  Green,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Square {
+  White,
+  Yellow,
+  Blue,
+  Green,
+  Red,
+  Orange,
+}
+
+impl Hex {
+  pub const ALL: [Hex; 8] = [
+    Hex::White,
+    Hex::Pink,
+    Hex::Red,
+    Hex::Blue,
+    Hex::Yellow,
+    Hex::Silver,
+    Hex::Orange,
+    Hex::Green,
+  ];
+}
+
+impl Hex {
+  /// Outward normal of the face on the solved puzzle, unnormalized.
+  pub fn normal(self) -> [i32; 3] {
+    match self {
+      Hex::White => [1, 1, 1],
+      Hex::Pink => [1, -1, -1],
+      Hex::Red => [-1, -1, 1],
+      Hex::Blue => [-1, 1, -1],
+      Hex::Yellow => [-1, -1, -1],
+      Hex::Silver => [-1, 1, 1],
+      Hex::Orange => [1, 1, -1],
+      Hex::Green => [1, -1, 1],
+    }
+  }
+
+  pub fn from_normal(normal: [i32; 3]) -> Option<Self> {
+    Self::ALL.into_iter().find(|h| h.normal() == normal)
+  }
+}
+
+impl Square {
+  pub const ALL: [Square; 6] = [
+    Square::White,
+    Square::Yellow,
+    Square::Blue,
+    Square::Green,
+    Square::Red,
+    Square::Orange,
+  ];
+
+  /// Outward normal of the face on the solved puzzle.
+  pub fn normal(self) -> [i32; 3] {
+    match self {
+      Square::Blue => [1, 0, 0],
+      Square::Green => [-1, 0, 0],
+      Square::Yellow => [0, 1, 0],
+      Square::White => [0, -1, 0],
+      Square::Orange => [0, 0, 1],
+      Square::Red => [0, 0, -1],
+    }
+  }
+
+  pub fn from_normal(normal: [i32; 3]) -> Option<Self> {
+    Self::ALL.into_iter().find(|s| s.normal() == normal)
+  }
+}
+
+/// A face of the solved puzzle, named by its color in the factory color scheme.
+/// Every facet takes its color from the face it started on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+  Square(Square),
+  Hex(Hex),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TriangleFacet(pub Hex);
+/// A trapezoid of a hexagon, carried by the square piece next to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EdgeFacet(pub Square, pub Hex);
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SquareFacet(pub Square);
+
+impl TriangleFacet {
+  pub fn face(self) -> Face {
+    Face::Hex(self.0)
+  }
+}
+
+impl EdgeFacet {
+  pub fn face(self) -> Face {
+    Face::Hex(self.1)
+  }
+}
+
+impl SquareFacet {
+  pub fn face(self) -> Face {
+    Face::Square(self.0)
+  }
+}
+
+// all possible facets for center cuts only puzzle.
+pub enum Facet {
+  Square(SquareFacet),
+  Triangle(TriangleFacet),
+  Edge(EdgeFacet),
+}
+
+/// The puzzle as a permutation of facets.
+/// Each permutation maps the index of a facet in `edges`, `squares` or `triangles`
+/// to the index of the slot it currently sits in.
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+  pub edges: Vec<EdgeFacet>,
+  pub squares: Vec<SquareFacet>,
+  pub triangles: Vec<TriangleFacet>,
+  pub edge_permutation: PermArray<24>,
+  pub square_permutation: PermArray<6>,
+  pub triangle_permutation: PermArray<8>,
+}
+
+impl Puzzle {
+  pub fn new() -> Self {
+    Self {
+      edges: edge_facets(),
+      squares: square_facets(),
+      triangles: triangle_facets(),
+      edge_permutation: PermArray::identity(),
+      square_permutation: PermArray::identity(),
+      triangle_permutation: PermArray::identity(),
+    }
+  }
+
+  pub fn twist(&mut self, twist: Twist) {
+    static TWISTS: OnceLock<[TwistPermutations; 8]> = OnceLock::new();
+    let twists = TWISTS.get_or_init(|| Twist::ALL.map(twist_permutations));
+    let index = Twist::ALL.iter().position(|t| *t == twist).unwrap();
+    let (edges, squares, triangles) = &twists[index];
+    self.edge_permutation = PermArray::compose(&self.edge_permutation, edges);
+    self.square_permutation = PermArray::compose(&self.square_permutation, squares);
+    self.triangle_permutation = PermArray::compose(&self.triangle_permutation, triangles);
+  }
+
+  /// Rebuild a puzzle from where its edges and triangles are.
+  /// The squares go wherever their trapezoids went.
+  pub fn from_permutations(edges: PermArray<24>, triangles: PermArray<8>) -> Result<Self, String> {
+    let mut puzzle = Self::new();
+    let mut squares = [None; 6];
+    for (i, EdgeFacet(square, _)) in puzzle.edges.iter().enumerate() {
+      let slot = puzzle.edges[edges.permute(i as u8) as usize].0;
+      let index = puzzle.squares.iter().position(|s| s.0 == *square).unwrap();
+      let target = puzzle.squares.iter().position(|s| s.0 == slot).unwrap() as u8;
+      if *squares[index].get_or_insert(target) != target {
+        return Err(format!(
+          "the trapezoids of the {square:?} square are torn apart"
+        ));
+      }
+    }
+    puzzle.square_permutation = PermArray::try_from(&squares.map(Option::unwrap)[..])?;
+    puzzle.edge_permutation = edges;
+    puzzle.triangle_permutation = triangles;
+    Ok(puzzle)
+  }
+
+  /// Edge then triangle permutations, 32 bytes that `from_bytes` reads back.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = self.edge_permutation.as_array().to_vec();
+    bytes.extend_from_slice(self.triangle_permutation.as_array());
+    bytes
+  }
+
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+    if bytes.len() != 32 {
+      return Err(format!("expected 32 bytes, got {}", bytes.len()));
+    }
+    Self::from_permutations(
+      PermArray::try_from(&bytes[..24])?,
+      PermArray::try_from(&bytes[24..])?,
+    )
+  }
+
+  pub fn is_solved(&self) -> bool {
+    self.edge_permutation == PermArray::identity()
+      && self.square_permutation == PermArray::identity()
+      && self.triangle_permutation == PermArray::identity()
+  }
+
+  /// The facet sitting in the slot that `edges[slot]` started in.
+  pub fn edge_at(&self, slot: usize) -> EdgeFacet {
+    self.edges[self.edge_permutation.invert().permute(slot as u8) as usize]
+  }
+
+  pub fn square_at(&self, slot: usize) -> SquareFacet {
+    self.squares[self.square_permutation.invert().permute(slot as u8) as usize]
+  }
+
+  pub fn triangle_at(&self, slot: usize) -> TriangleFacet {
+    self.triangles[self.triangle_permutation.invert().permute(slot as u8) as usize]
+  }
+}
+
+impl Default for Puzzle {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+pub type TwistPermutations = (PermArray<24>, PermArray<6>, PermArray<8>);
+
+/// Where a twist sends each slot, indexed like `edge_facets`, `square_facets`
+/// and `triangle_facets`.
+pub fn twist_permutations(twist: Twist) -> TwistPermutations {
+  let axis = twist.to_normal().map(|c| c as i32);
+  let moves = |normal: [i32; 3]| dot(axis, normal) > 0;
+  let turn = |normal: [i32; 3]| rotate(twist, axis, normal);
+
+  let edges = edge_facets();
+  let edge_slots = std::array::from_fn(|i| {
+    let EdgeFacet(square, hex) = edges[i];
+    if !moves(square.normal()) {
+      return i as u8;
+    }
+    let target = EdgeFacet(
+      Square::from_normal(turn(square.normal())).unwrap(),
+      Hex::from_normal(turn(hex.normal())).unwrap(),
+    );
+    edges.iter().position(|e| *e == target).unwrap() as u8
+  });
+
+  let squares = square_facets();
+  let square_slots = std::array::from_fn(|i| {
+    let SquareFacet(square) = squares[i];
+    let target = if moves(square.normal()) {
+      Square::from_normal(turn(square.normal())).unwrap()
+    } else {
+      square
+    };
+    squares.iter().position(|s| s.0 == target).unwrap() as u8
+  });
+
+  let triangles = triangle_facets();
+  let triangle_slots = std::array::from_fn(|i| {
+    let TriangleFacet(hex) = triangles[i];
+    let target = if moves(hex.normal()) {
+      Hex::from_normal(turn(hex.normal())).unwrap()
+    } else {
+      hex
+    };
+    triangles.iter().position(|t| t.0 == target).unwrap() as u8
+  });
+
+  (
+    PermArray::from(edge_slots),
+    PermArray::from(square_slots),
+    PermArray::from(triangle_slots),
+  )
+}
+
+fn dot(a: [i32; 3], b: [i32; 3]) -> i32 {
+  a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Rotate `v` a third of a turn about `axis`, a diagonal like (1, 1, 1).
+/// Rodrigues' formula with cos = -1/2 and sin = sqrt(3)/2 stays integral:
+/// 2v' = -v ± axis × v + (axis · v) axis
+fn rotate(twist: Twist, axis: [i32; 3], v: [i32; 3]) -> [i32; 3] {
+  let sign = if twist.positive() { 1 } else { -1 };
+  let cross = [
+    axis[1] * v[2] - axis[2] * v[1],
+    axis[2] * v[0] - axis[0] * v[2],
+    axis[0] * v[1] - axis[1] * v[0],
+  ];
+  let d = dot(axis, v);
+  std::array::from_fn(|i| (-v[i] + sign * cross[i] + d * axis[i]) / 2)
+}
+
+/*
+fn facets() -> Vec<Facet> {
+  triangle_facets()
+    .map(|t| Facet::Triangle(facet))
+    .concat(square_facets())
+    .concat(edge_facets())
+}
+*/
+
+pub fn square_facets() -> Vec<SquareFacet> {
+  vec![
+    SquareFacet(Square::White),
+    SquareFacet(Square::Yellow),
+    SquareFacet(Square::Blue),
+    SquareFacet(Square::Green),
+    SquareFacet(Square::Red),
+    SquareFacet(Square::Orange),
+  ]
+}
+
+pub fn triangle_facets() -> Vec<TriangleFacet> {
+  vec![
+    TriangleFacet(Hex::White),
+    TriangleFacet(Hex::Pink),
+    TriangleFacet(Hex::Red),
+    TriangleFacet(Hex::Blue),
+    TriangleFacet(Hex::Yellow),
+    TriangleFacet(Hex::Silver),
+    TriangleFacet(Hex::Orange),
+    TriangleFacet(Hex::Green),
+  ]
+}
+
+pub fn edge_facets() -> Vec<EdgeFacet> {
+  vec![
+    EdgeFacet(Square::Blue, Hex::White),
+    EdgeFacet(Square::Blue, Hex::Orange),
+    EdgeFacet(Square::Blue, Hex::Pink),
+    EdgeFacet(Square::Blue, Hex::Green),
+    //
+    EdgeFacet(Square::Yellow, Hex::White),
+    EdgeFacet(Square::Yellow, Hex::Silver),
+    EdgeFacet(Square::Yellow, Hex::Blue),
+    EdgeFacet(Square::Yellow, Hex::Orange),
+    //
+    EdgeFacet(Square::Orange, Hex::White),
+    EdgeFacet(Square::Orange, Hex::Green),
+    EdgeFacet(Square::Orange, Hex::Red),
+    EdgeFacet(Square::Orange, Hex::Silver),
+    //
+    EdgeFacet(Square::Green, Hex::Yellow),
+    EdgeFacet(Square::Green, Hex::Blue),
+    EdgeFacet(Square::Green, Hex::Silver),
+    EdgeFacet(Square::Green, Hex::Red),
+    //
+    EdgeFacet(Square::Red, Hex::Yellow),
+    EdgeFacet(Square::Red, Hex::Pink),
+    EdgeFacet(Square::Red, Hex::Orange),
+    EdgeFacet(Square::Red, Hex::Blue),
+    //
+    EdgeFacet(Square::White, Hex::Yellow),
+    EdgeFacet(Square::White, Hex::Red),
+    EdgeFacet(Square::White, Hex::Green),
+    EdgeFacet(Square::White, Hex::Pink),
+  ]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn twists_have_order_three() {
+    for twist in Twist::ALL {
+      let mut puzzle = Puzzle::new();
+      puzzle.twist(twist);
+      assert!(!puzzle.is_solved());
+      puzzle.twist(twist);
+      puzzle.twist(twist);
+      assert!(puzzle.is_solved());
+    }
+  }
+
+  #[test]
+  fn inverse_undoes_twist() {
+    for twist in Twist::ALL {
+      let mut puzzle = Puzzle::new();
+      puzzle.twist(twist);
+      puzzle.twist(twist.inverse());
+      assert!(puzzle.is_solved());
+    }
+  }
+
+  #[test]
+  fn bytes_round_trip() {
+    let mut puzzle = Puzzle::new();
+    for twist in [Twist::ALL[0], Twist::ALL[5], Twist::ALL[2]] {
+      puzzle.twist(twist);
+    }
+    let copy = Puzzle::from_bytes(&puzzle.to_bytes()).unwrap();
+    assert_eq!(copy.edge_permutation, puzzle.edge_permutation);
+    assert_eq!(copy.square_permutation, puzzle.square_permutation);
+    assert_eq!(copy.triangle_permutation, puzzle.triangle_permutation);
+  }
+
+  #[test]
+  fn squares_hold_their_trapezoids() {
+    let mut bytes = Puzzle::new().to_bytes();
+    bytes.swap(0, 4);
+    assert!(Puzzle::from_bytes(&bytes).is_err());
+  }
+
+  #[test]
+  fn twist_cycles_three_squares_and_triangles() {
+    let (edges, squares, triangles) = twist_permutations(Twist::ALL[0]);
+    let moved = |slots: Vec<u8>| {
+      slots
+        .iter()
+        .enumerate()
+        .filter(|(i, s)| *i as u8 != **s)
+        .count()
+    };
+    assert_eq!(moved((0..24).map(|i| edges.permute(i)).collect()), 12);
+    assert_eq!(moved((0..6).map(|i| squares.permute(i)).collect()), 3);
+    assert_eq!(moved((0..8).map(|i| triangles.permute(i)).collect()), 3);
+  }
+}
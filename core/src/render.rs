@@ -0,0 +1,93 @@
+use crate::color::Color;
+use crate::state::State;
+use gl_matrix::common::Mat4;
+use gl_matrix::mat4;
+
+/// What's drawn behind the puzzle.
+#[derive(Clone, Copy, Debug)]
+pub enum Background {
+  Solid(Color),
+  /// Blends from the top of the screen to the bottom.
+  Gradient {
+    top: Color,
+    bottom: Color,
+  },
+  /// A sky above a horizon, which turns with the camera.
+  Environment,
+}
+
+impl Background {
+  // easier on the eyes than pure black
+  pub const DEFAULT: Self = Self::Gradient {
+    top: Color::rgb(46, 52, 66),
+    bottom: Color::rgb(14, 16, 22),
+  };
+}
+
+/// Options for a frame that every backend understands.
+#[derive(Clone, Copy, Debug)]
+pub struct Scene {
+  pub background: Background,
+  /// Overlay a pattern on each facet, see `pattern::atlas`.
+  pub patterns: bool,
+  /// Darken the floor beneath the puzzle.
+  pub ground_shadow: bool,
+}
+
+impl Default for Scene {
+  fn default() -> Self {
+    Self {
+      background: Background::DEFAULT,
+      patterns: false,
+      ground_shadow: false,
+    }
+  }
+}
+
+/// Something the puzzle can be drawn with.
+/// Vertex data comes as flat arrays, laid out the way webgl buffers expect them.
+/// Backends hold on to it between frames,
+/// so only what changed needs to be set before the next draw.
+pub trait Backend {
+  type Error;
+
+  /// Rotation of the puzzle. Backends apply their own projection on top of it.
+  fn set_camera(&mut self, camera: &Mat4);
+
+  /// 3 coordinates per vertex
+  fn set_vertex_positions(&mut self, positions: &[f32]);
+
+  /// rgba per vertex
+  fn set_vertex_colors(&mut self, colors: &[f32]);
+
+  /// Texture coordinates and an atlas tile per vertex.
+  fn set_vertex_patterns(&mut self, uvs: &[f32], patterns: &[f32]);
+
+  /// Triangles, as triples of vertex indices.
+  fn set_vertex_indices(&mut self, indices: &[u32]);
+
+  fn draw(&mut self, scene: &Scene) -> Result<(), Self::Error>;
+}
+
+/// Upload everything about the state to the backend.
+pub fn upload<B: Backend>(backend: &mut B, state: &State) {
+  backend.set_camera(&state.camera_transform);
+  backend.set_vertex_colors(&state.get_vertex_colors());
+  backend.set_vertex_patterns(&state.get_vertex_uvs(), &state.get_vertex_patterns());
+  backend.set_vertex_indices(&state.get_vertex_indices());
+  backend.set_vertex_positions(&state.get_vertex_positions());
+}
+
+/// Perspective for a viewer looking at the puzzle from 6 units away.
+pub fn projection_matrix(aspect: f32) -> Mat4 {
+  let fov = (45.0 * std::f64::consts::PI as f32) / 180.0;
+  let near = 0.1;
+  let far = 100.0;
+  let mut p = mat4::create();
+  mat4::perspective(&mut p, fov, aspect, near, Some(far));
+  let mut t = mat4::create();
+  mat4::from_translation(&mut t, &[0.0, 0.0, -6.0]);
+  let mut dest = mat4::create();
+  mat4::multiply(&mut dest, &p, &t);
+  dest
+}
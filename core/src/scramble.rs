@@ -0,0 +1,106 @@
+use crate::group;
+use crate::notation;
+use crate::puzzle::Puzzle;
+use crate::solver::Solver;
+use crate::state::Twist;
+
+/// SplitMix64, so a seed gives the same scramble on every platform.
+pub struct Rng(u64);
+
+impl Rng {
+  pub fn new(seed: u64) -> Self {
+    Self(seed)
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+  }
+
+  /// A uniformly random number less than `n`.
+  pub fn below(&mut self, n: usize) -> usize {
+    let n = n as u64;
+    // reject the top partial range, which would favor small numbers
+    let zone = u64::MAX - u64::MAX % n;
+    loop {
+      let x = self.next_u64();
+      if x < zone {
+        return (x % n) as usize;
+      }
+    }
+  }
+}
+
+/// A state picked uniformly among every reachable one,
+/// and a shortest sequence of twists from solved that reaches it.
+pub struct Scramble {
+  pub seed: u64,
+  pub state: Puzzle,
+  pub twists: Vec<Twist>,
+}
+
+pub fn scramble(solver: &Solver, seed: u64) -> Scramble {
+  let mut rng = Rng::new(seed);
+  let state = group::puzzle(&group::chain().random(|n| rng.below(n)));
+  let twists = notation::inverse(&solver.solve(&state));
+  Scramble {
+    seed,
+    state,
+    twists,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scrambles_reach_their_state() {
+    let solver = Solver::new();
+    for seed in 0..50 {
+      let scramble = scramble(&solver, seed);
+      assert!(group::is_reachable(&scramble.state));
+      assert!(scramble.twists.len() <= 10);
+      let mut puzzle = Puzzle::new();
+      for twist in &scramble.twists {
+        puzzle.twist(*twist);
+      }
+      assert_eq!(puzzle.edge_permutation, scramble.state.edge_permutation);
+      assert_eq!(puzzle.square_permutation, scramble.state.square_permutation);
+      assert_eq!(
+        puzzle.triangle_permutation,
+        scramble.state.triangle_permutation
+      );
+    }
+  }
+
+  #[test]
+  fn seeds_are_reproducible() {
+    let solver = Solver::new();
+    assert_eq!(scramble(&solver, 42).twists, scramble(&solver, 42).twists);
+    assert_ne!(scramble(&solver, 42).twists, scramble(&solver, 43).twists);
+  }
+
+  #[test]
+  fn most_states_are_far_from_solved() {
+    // 92% of states are 6 or more twists away
+    let solver = Solver::new();
+    let far = (100..200)
+      .filter(|seed| scramble(&solver, *seed).twists.len() >= 6)
+      .count();
+    assert!(far > 80, "{far}");
+  }
+
+  #[test]
+  fn below_stays_in_range() {
+    let mut rng = Rng::new(3);
+    let mut seen = [false; 5];
+    for _ in 0..100 {
+      seen[rng.below(5)] = true;
+    }
+    assert!(seen.iter().all(|s| *s));
+  }
+}
@@ -0,0 +1,160 @@
+use crate::codec;
+use crate::notation;
+use crate::puzzle::Puzzle;
+use crate::state::Twist;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// A position read from a link.
+#[derive(Clone, Debug)]
+pub struct Shared {
+  pub puzzle: Puzzle,
+  /// The moves that led there, when the link carries them.
+  pub history: Vec<Twist>,
+}
+
+/// A URL fragment, without the `#`, that `decode` reads back:
+/// `s=` and the puzzle's `codec::encode` bytes in URL safe base64,
+/// then optionally `&m=` and the moves without spaces, e.g. `s=AM4g&m=HJ'K`.
+/// Fails for states no sequence of twists reaches.
+pub fn encode(puzzle: &Puzzle, history: Option<&[Twist]>) -> Result<String, String> {
+  let mut fragment = format!("s={}", base64(&codec::encode(puzzle)?));
+  if let Some(history) = history.filter(|h| !h.is_empty()) {
+    fragment.push_str("&m=");
+    fragment.extend(history.iter().map(|t| notation::name(*t)));
+  }
+  Ok(fragment)
+}
+
+/// Read a fragment written by `encode`, with or without its `#`.
+/// Fails for garbled links and for positions no sequence of twists reaches.
+pub fn decode(fragment: &str) -> Result<Shared, String> {
+  let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+  let mut state = None;
+  let mut history = Vec::new();
+  for part in fragment.split('&').filter(|p| !p.is_empty()) {
+    match part.split_once('=') {
+      Some(("s", value)) => state = Some(unbase64(value)?),
+      Some(("m", value)) => history = notation::parse(&value.replace("%27", "'"))?,
+      _ => return Err(format!("unknown part `{part}` in the link")),
+    }
+  }
+  let bytes = state.ok_or("the link has no puzzle state")?;
+  let puzzle = if bytes.len() == codec::BYTES {
+    codec::decode(&bytes)?
+  } else {
+    // links from before the codec carry `Puzzle::to_bytes`
+    let puzzle = Puzzle::from_bytes(&bytes)?;
+    codec::rank(&puzzle).map_err(|_| {
+      "the linked puzzle can't be solved: no sequence of twists reaches it".to_string()
+    })?;
+    puzzle
+  };
+  Ok(Shared { puzzle, history })
+}
+
+fn base64(bytes: &[u8]) -> String {
+  let mut out = String::new();
+  for chunk in bytes.chunks(3) {
+    let n = chunk
+      .iter()
+      .enumerate()
+      .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+    for i in 0..=chunk.len() {
+      out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+    }
+  }
+  out
+}
+
+fn unbase64(text: &str) -> Result<Vec<u8>, String> {
+  let digits = text
+    .bytes()
+    .map(|c| {
+      ALPHABET
+        .iter()
+        .position(|a| *a == c)
+        .map(|d| d as u32)
+        .ok_or_else(|| format!("`{}` is not base64", c as char))
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  let mut bytes = Vec::new();
+  for chunk in digits.chunks(4) {
+    if chunk.len() == 1 {
+      return Err("the base64 is cut short".to_string());
+    }
+    let n = chunk
+      .iter()
+      .enumerate()
+      .fold(0, |n, (i, d)| n | d << (18 - 6 * i));
+    for i in 0..chunk.len() - 1 {
+      bytes.push((n >> (16 - 8 * i)) as u8);
+    }
+  }
+  Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::permutation::PermArray;
+
+  fn scrambled() -> (Puzzle, Vec<Twist>) {
+    let twists = notation::parse("H J' K L' H").unwrap();
+    let mut puzzle = Puzzle::new();
+    for twist in &twists {
+      puzzle.twist(*twist);
+    }
+    (puzzle, twists)
+  }
+
+  #[test]
+  fn round_trip() {
+    let (puzzle, twists) = scrambled();
+    let fragment = encode(&puzzle, Some(&twists)).unwrap();
+    // three bytes of rank are four base64 characters
+    assert_eq!(fragment.find('&'), Some(6), "{fragment}");
+    assert!(fragment.ends_with("&m=HJ'KL'H"), "{fragment}");
+    let shared = decode(&format!("#{fragment}")).unwrap();
+    assert_eq!(shared.puzzle.to_bytes(), puzzle.to_bytes());
+    assert_eq!(shared.history, twists);
+
+    let shared = decode(&encode(&puzzle, None).unwrap()).unwrap();
+    assert!(shared.history.is_empty());
+  }
+
+  #[test]
+  fn reads_links_from_before_the_codec() {
+    let (puzzle, _) = scrambled();
+    let shared = decode(&format!("s={}", base64(&puzzle.to_bytes()))).unwrap();
+    assert_eq!(shared.puzzle.to_bytes(), puzzle.to_bytes());
+  }
+
+  #[test]
+  fn base64_round_trip() {
+    for n in 0..8 {
+      let bytes: Vec<u8> = (0..n).map(|i: u8| i.wrapping_mul(37) ^ 0xc8).collect();
+      assert_eq!(unbase64(&base64(&bytes)), Ok(bytes));
+    }
+    assert_eq!(base64(b"Man"), "TWFu");
+  }
+
+  #[test]
+  fn reports_bad_links() {
+    assert!(decode("").is_err());
+    assert!(decode("s=!!").is_err());
+    assert!(decode("s=AAA").is_err());
+    assert!(decode("s=AAAA").unwrap().puzzle.is_solved());
+    assert!(decode("x=1").is_err());
+
+    // past the last rank
+    assert!(decode("s=____").is_err());
+
+    // two triangles swapped
+    let mut puzzle = Puzzle::new();
+    puzzle.triangle_permutation = PermArray::from([1, 0, 2, 3, 4, 5, 6, 7]);
+    assert!(encode(&puzzle, None).is_err());
+    let error = decode(&format!("s={}", base64(&puzzle.to_bytes()))).unwrap_err();
+    assert!(error.contains("can't be solved"), "{error}");
+  }
+}
@@ -0,0 +1,393 @@
+use crate::color::Color;
+use crate::pattern;
+use crate::render::{self, Backend, Background, Scene};
+use gl_matrix::common::{Mat4, Vec3, Vec4};
+use gl_matrix::{mat4, vec3, vec4};
+use std::convert::Infallible;
+
+/// Rasterizes on the cpu into an rgba buffer,
+/// so frames can be rendered without a browser or a gpu.
+/// Mirrors the webgl shaders closely enough to stand in for them in tests.
+pub struct Software {
+  width: usize,
+  height: usize,
+  projection: Mat4,
+  camera: Mat4,
+  positions: Vec<f32>,
+  colors: Vec<f32>,
+  uvs: Vec<f32>,
+  patterns: Vec<f32>,
+  indices: Vec<u32>,
+  atlas: Vec<u8>,
+  pixels: Vec<u8>,
+  depth: Vec<f32>,
+}
+
+// A vertex after projection.
+#[derive(Clone, Copy)]
+struct Projected {
+  // pixel coordinates, origin at the top left
+  x: f32,
+  y: f32,
+  // normalized device depth
+  z: f32,
+  // reciprocal of the clip space w, for perspective correct interpolation
+  inv_w: f32,
+}
+
+impl Software {
+  pub fn new(width: usize, height: usize) -> Self {
+    let mut camera = mat4::create();
+    mat4::identity(&mut camera);
+    Self {
+      width,
+      height,
+      projection: render::projection_matrix(width as f32 / height as f32),
+      camera,
+      positions: vec![],
+      colors: vec![],
+      uvs: vec![],
+      patterns: vec![],
+      indices: vec![],
+      atlas: pattern::atlas(),
+      pixels: vec![0; 4 * width * height],
+      depth: vec![f32::INFINITY; width * height],
+    }
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  /// Rgba pixels of the last frame drawn, rows from top to bottom.
+  pub fn pixels(&self) -> &[u8] {
+    &self.pixels
+  }
+
+  fn transform(&self) -> Mat4 {
+    let mut transform = mat4::create();
+    mat4::multiply(&mut transform, &self.projection, &self.camera);
+    transform
+  }
+
+  fn put_pixel(&mut self, x: usize, y: usize, rgb: Vec3) {
+    let i = 4 * (y * self.width + x);
+    for (channel, value) in rgb.iter().enumerate() {
+      self.pixels[i + channel] = (value.clamp(0., 1.) * 255.).round() as u8;
+    }
+    self.pixels[i + 3] = 255;
+  }
+
+  // see BACKGROUND_SHADER
+  fn draw_background(&mut self, scene: &Scene) {
+    let inverse_transform = invert(&self.transform());
+    let inverse_projection = invert(&self.projection);
+    for y in 0..self.height {
+      for x in 0..self.width {
+        let u = (x as f32 + 0.5) / self.width as f32;
+        let v = 1. - (y as f32 + 0.5) / self.height as f32;
+        let ndc = [2. * u - 1., 2. * v - 1.];
+
+        let mut color = match scene.background {
+          Background::Solid(color) => rgb(color),
+          Background::Gradient { top, bottom } => mix(rgb(bottom), rgb(top), v),
+          Background::Environment => environment(ray(&inverse_transform, ndc)),
+        };
+
+        if scene.ground_shadow {
+          let origin = unproject(&inverse_projection, ndc, -1.);
+          let dir = ray(&inverse_projection, ndc);
+          if dir[1] < 0. {
+            let t = (FLOOR - origin[1]) / dir[1];
+            let hit = [origin[0] + t * dir[0], origin[2] + t * dir[2]];
+            let distance = (hit[0] * hit[0] + hit[1] * hit[1]).sqrt();
+            let shade = 1. - 0.6 * (1. - smoothstep(0.4, 2.2, distance));
+            color = color.map(|c| c * shade);
+          }
+        }
+        self.put_pixel(x, y, color);
+      }
+    }
+  }
+
+  fn project(&self, transform: &Mat4, index: usize) -> Option<Projected> {
+    let position = &self.positions[3 * index..3 * index + 3];
+    let mut clip = vec4::create();
+    vec4::transform_mat4(
+      &mut clip,
+      &[position[0], position[1], position[2], 1.],
+      transform,
+    );
+    // behind the eye; never happens with the default camera distance
+    if clip[3] <= 0. {
+      return None;
+    }
+    let inv_w = 1. / clip[3];
+    Some(Projected {
+      x: (clip[0] * inv_w + 1.) / 2. * self.width as f32,
+      y: (1. - clip[1] * inv_w) / 2. * self.height as f32,
+      z: clip[2] * inv_w,
+      inv_w,
+    })
+  }
+
+  // see FRAGMENT_SHADER
+  fn shade(&self, vertices: [usize; 3], weights: Vec3, patterns: bool) -> Vec3 {
+    let interpolate = |data: &[f32], stride: usize, component: usize| {
+      (0..3)
+        .map(|k| weights[k] * data[stride * vertices[k] + component])
+        .sum::<f32>()
+    };
+    let color = [0, 1, 2].map(|c| interpolate(&self.colors, 4, c));
+    if !patterns {
+      return color;
+    }
+
+    let tile = self.patterns[vertices[0]].round() as usize;
+    let u = interpolate(&self.uvs, 2, 0).rem_euclid(1.);
+    let v = interpolate(&self.uvs, 2, 1).rem_euclid(1.);
+    let x = (tile % pattern::ATLAS_COLUMNS) * pattern::TILE_SIZE
+      + ((u * pattern::TILE_SIZE as f32) as usize).min(pattern::TILE_SIZE - 1);
+    let y = (tile / pattern::ATLAS_COLUMNS) * pattern::TILE_SIZE
+      + ((v * pattern::TILE_SIZE as f32) as usize).min(pattern::TILE_SIZE - 1);
+    let mask = f32::from(self.atlas[4 * (y * pattern::ATLAS_WIDTH + x) + 3]) / 255.;
+
+    let luma = vec3::dot(&color, &[0.299, 0.587, 0.114]);
+    let ink = if luma > 0.5 { [0.; 3] } else { [1.; 3] };
+    mix(color, ink, 0.6 * mask)
+  }
+
+  fn draw_triangle(&mut self, transform: &Mat4, vertices: [usize; 3], patterns: bool) {
+    let [Some(a), Some(b), Some(c)] = vertices.map(|v| self.project(transform, v)) else {
+      return;
+    };
+    let area = edge(a, b, c.x, c.y);
+    if area == 0. {
+      return;
+    }
+
+    let min_x = a.x.min(b.x).min(c.x).floor().max(0.) as usize;
+    let min_y = a.y.min(b.y).min(c.y).floor().max(0.) as usize;
+    let max_x = (a.x.max(b.x).max(c.x).ceil() as usize).min(self.width);
+    let max_y = (a.y.max(b.y).max(c.y).ceil() as usize).min(self.height);
+
+    for y in min_y..max_y {
+      for x in min_x..max_x {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        // barycentric coordinates in screen space, for either winding
+        let screen = [
+          edge(b, c, px, py) / area,
+          edge(c, a, px, py) / area,
+          edge(a, b, px, py) / area,
+        ];
+        if screen.iter().any(|w| *w < 0.) {
+          continue;
+        }
+
+        let z = screen[0] * a.z + screen[1] * b.z + screen[2] * c.z;
+        let i = y * self.width + x;
+        if !(-1. ..=1.).contains(&z) || z > self.depth[i] {
+          continue;
+        }
+        self.depth[i] = z;
+
+        let mut weights = [
+          screen[0] * a.inv_w,
+          screen[1] * b.inv_w,
+          screen[2] * c.inv_w,
+        ];
+        let total: f32 = weights.iter().sum();
+        weights = weights.map(|w| w / total);
+        let color = self.shade(vertices, weights, patterns);
+        self.put_pixel(x, y, color);
+      }
+    }
+  }
+}
+
+impl Backend for Software {
+  type Error = Infallible;
+
+  fn set_camera(&mut self, camera: &Mat4) {
+    self.camera = *camera;
+  }
+
+  fn set_vertex_positions(&mut self, positions: &[f32]) {
+    self.positions = positions.to_vec();
+  }
+
+  fn set_vertex_colors(&mut self, colors: &[f32]) {
+    self.colors = colors.to_vec();
+  }
+
+  fn set_vertex_patterns(&mut self, uvs: &[f32], patterns: &[f32]) {
+    self.uvs = uvs.to_vec();
+    self.patterns = patterns.to_vec();
+  }
+
+  fn set_vertex_indices(&mut self, indices: &[u32]) {
+    self.indices = indices.to_vec();
+  }
+
+  fn draw(&mut self, scene: &Scene) -> Result<(), Infallible> {
+    self.depth.fill(f32::INFINITY);
+    self.draw_background(scene);
+
+    let transform = self.transform();
+    for i in 0..self.indices.len() / 3 {
+      let triangle = &self.indices[3 * i..3 * i + 3];
+      let vertices = [0, 1, 2].map(|k| triangle[k] as usize);
+      self.draw_triangle(&transform, vertices, scene.patterns);
+    }
+    Ok(())
+  }
+}
+
+// height of the floor the ground shadow falls on
+const FLOOR: f32 = -2.2;
+
+fn edge(a: Projected, b: Projected, x: f32, y: f32) -> f32 {
+  (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+fn rgb(color: Color) -> Vec3 {
+  [color.red(), color.green(), color.blue()]
+}
+
+fn mix(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+  [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+  let t = ((x - edge0) / (edge1 - edge0)).clamp(0., 1.);
+  t * t * (3. - 2. * t)
+}
+
+fn invert(matrix: &Mat4) -> Mat4 {
+  let mut inverse = mat4::create();
+  mat4::invert(&mut inverse, matrix);
+  inverse
+}
+
+fn unproject(inverse: &Mat4, ndc: [f32; 2], depth: f32) -> Vec3 {
+  let mut point: Vec4 = vec4::create();
+  vec4::transform_mat4(&mut point, &[ndc[0], ndc[1], depth, 1.], inverse);
+  [
+    point[0] / point[3],
+    point[1] / point[3],
+    point[2] / point[3],
+  ]
+}
+
+fn ray(inverse: &Mat4, ndc: [f32; 2]) -> Vec3 {
+  let near = unproject(inverse, ndc, -1.);
+  let far = unproject(inverse, ndc, 1.);
+  let mut dir = vec3::create();
+  vec3::subtract(&mut dir, &far, &near);
+  let unnormalized = dir;
+  vec3::normalize(&mut dir, &unnormalized);
+  dir
+}
+
+fn environment(dir: Vec3) -> Vec3 {
+  let zenith = [0.18, 0.32, 0.58];
+  let horizon = [0.72, 0.78, 0.85];
+  let ground = [0.22, 0.2, 0.18];
+  if dir[1] > 0. {
+    let sky = mix(horizon, zenith, dir[1].sqrt());
+    let mut sun = vec3::create();
+    vec3::normalize(&mut sun, &[0.4, 0.7, 0.6]);
+    let glow = vec3::dot(&dir, &sun).max(0.).powf(64.);
+    let tint = [1.0, 0.9, 0.7];
+    [0, 1, 2].map(|i| sky[i] + tint[i] * glow)
+  } else {
+    mix(horizon.map(|c| c * 0.6), ground, (-dir[1]).sqrt())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::state::{State, Twist, ANIMATION_DURATION};
+  use std::path::PathBuf;
+
+  const SIZE: usize = 80;
+
+  fn render(state: &State, scene: &Scene) -> Software {
+    let mut backend = Software::new(SIZE, SIZE);
+    render::upload(&mut backend, state);
+    backend.draw(scene).unwrap();
+    backend
+  }
+
+  // Compares against a binary ppm under tests/golden.
+  // Run with UPDATE_GOLDEN=1 to accept the current output instead.
+  fn assert_golden(name: &str, backend: &Software) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+      .iter()
+      .collect();
+    let header = format!("P6\n{} {}\n255\n", backend.width(), backend.height());
+    let rgb: Vec<u8> = backend
+      .pixels()
+      .chunks_exact(4)
+      .flat_map(|p| [p[0], p[1], p[2]])
+      .collect();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+      let mut file = header.into_bytes();
+      file.extend_from_slice(&rgb);
+      std::fs::write(&path, file).unwrap();
+      return;
+    }
+
+    let golden = std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    let expected = golden
+      .strip_prefix(header.as_bytes())
+      .unwrap_or_else(|| panic!("{name} has a different size"));
+    // leave room for rounding differences along facet edges
+    let mismatched = rgb
+      .chunks_exact(3)
+      .zip(expected.chunks_exact(3))
+      .filter(|(a, b)| a.iter().zip(b.iter()).any(|(x, y)| x.abs_diff(*y) > 8))
+      .count();
+    assert!(
+      mismatched * 100 <= SIZE * SIZE,
+      "{name}: {mismatched} pixels differ from the golden image"
+    );
+  }
+
+  #[test]
+  fn solved() {
+    assert_golden("solved.ppm", &render(&State::new(), &Scene::default()));
+  }
+
+  #[test]
+  fn twisted() {
+    let mut state = State::new();
+    state.twist_buffer.push_back(Twist::Center { octant: 7 });
+    state.advance(ANIMATION_DURATION + 1.);
+    assert_golden("twisted.ppm", &render(&state, &Scene::default()));
+  }
+
+  #[test]
+  fn mid_twist() {
+    let mut state = State::new();
+    state.twist_buffer.push_back(Twist::Center { octant: 2 });
+    state.advance(ANIMATION_DURATION / 2.);
+    assert_golden("mid_twist.ppm", &render(&state, &Scene::default()));
+  }
+
+  #[test]
+  fn patterns_and_environment() {
+    let scene = Scene {
+      background: Background::Environment,
+      patterns: true,
+      ground_shadow: true,
+    };
+    assert_golden("patterns.ppm", &render(&State::new(), &scene));
+  }
+}
@@ -0,0 +1,294 @@
+use crate::permutation::PermArray;
+use crate::puzzle::{twist_permutations, Puzzle};
+use crate::state::Twist;
+use std::collections::{HashMap, VecDeque};
+
+/// Finds shortest move sequences with IDA*.
+/// Solving takes at least as many twists as the square pieces need on their own,
+/// and as many as the triangles need on their own.
+/// Both are looked up in tables built by breadth first search and bound the search from below.
+/// The square pieces are tracked through their trapezoids, which also give their orientation.
+pub struct Solver {
+  twists: [(Twist, PermArray<24>, PermArray<8>); 8],
+  edge_distance: HashMap<PermArray<24>, u8>,
+  triangle_distance: HashMap<PermArray<8>, u8>,
+}
+
+impl Default for Solver {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Solver {
+  pub fn new() -> Self {
+    let twists = Twist::ALL.map(|twist| {
+      let (edges, _, triangles) = twist_permutations(twist);
+      (twist, edges, triangles)
+    });
+    let edge_distance = distances(twists.each_ref().map(|(_, edges, _)| edges.clone()));
+    let triangle_distance = distances(twists.each_ref().map(|(_, _, triangles)| triangles.clone()));
+    Self {
+      twists,
+      edge_distance,
+      triangle_distance,
+    }
+  }
+
+  /// A shortest sequence of twists that solves the puzzle.
+  pub fn solve(&self, puzzle: &Puzzle) -> Vec<Twist> {
+    let mut search = self
+      .start(puzzle)
+      .expect("twists only reach solvable states");
+    loop {
+      if let Some(solution) = search.step(self) {
+        return solution;
+      }
+    }
+  }
+
+  /// Begin a search that runs one depth at a time, see `Search::step`.
+  /// Fails for states no sequence of twists reaches.
+  pub fn start(&self, puzzle: &Puzzle) -> Result<Search, String> {
+    let edges = puzzle.edge_permutation.clone();
+    let triangles = puzzle.triangle_permutation.clone();
+    if !self.edge_distance.contains_key(&edges) || !self.triangle_distance.contains_key(&triangles)
+    {
+      return Err("the puzzle can't be solved from this state".to_string());
+    }
+    let depth = self.estimate(&edges, &triangles);
+    Ok(Search {
+      edges,
+      triangles,
+      depth,
+    })
+  }
+
+  /// The first twist of a shortest solution, or nothing if the puzzle is solved.
+  pub fn hint(&self, puzzle: &Puzzle) -> Option<Twist> {
+    self.solve(puzzle).first().copied()
+  }
+
+  /// Number of twists in an optimal solution.
+  pub fn distance(&self, puzzle: &Puzzle) -> usize {
+    self.solve(puzzle).len()
+  }
+
+  fn estimate(&self, edges: &PermArray<24>, triangles: &PermArray<8>) -> u8 {
+    self.edge_distance[edges].max(self.triangle_distance[triangles])
+  }
+
+  // depth first search for a solution of at most `bound` more twists
+  fn search(
+    &self,
+    edges: &PermArray<24>,
+    triangles: &PermArray<8>,
+    bound: u8,
+    path: &mut Vec<Twist>,
+  ) -> bool {
+    let estimate = self.estimate(edges, triangles);
+    if estimate == 0 {
+      return true;
+    }
+    if estimate > bound {
+      return false;
+    }
+
+    for (twist, twist_edges, twist_triangles) in &self.twists {
+      // two twists about the same axis make one
+      if let Some(last) = path.last() {
+        if last.to_normal() == twist.to_normal() {
+          continue;
+        }
+      }
+      path.push(*twist);
+      let edges = PermArray::compose(edges, twist_edges);
+      let triangles = PermArray::compose(triangles, twist_triangles);
+      if self.search(&edges, &triangles, bound - 1, path) {
+        return true;
+      }
+      path.pop();
+    }
+    false
+  }
+}
+
+/// An optimal solve in progress.
+/// Each step searches every sequence of one length, shortest first,
+/// so callers can report progress or give up between steps.
+pub struct Search {
+  edges: PermArray<24>,
+  triangles: PermArray<8>,
+  depth: u8,
+}
+
+impl Search {
+  /// Length of the sequences the next step tries.
+  pub fn depth(&self) -> u8 {
+    self.depth
+  }
+
+  pub fn step(&mut self, solver: &Solver) -> Option<Vec<Twist>> {
+    let mut path = Vec::new();
+    if solver.search(&self.edges, &self.triangles, self.depth, &mut path) {
+      Some(path)
+    } else {
+      self.depth += 1;
+      None
+    }
+  }
+}
+
+/// Twists needed to solve every reachable permutation, by breadth first search from solved.
+fn distances<const N: usize>(twists: [PermArray<N>; 8]) -> HashMap<PermArray<N>, u8> {
+  let mut distance = HashMap::from([(PermArray::identity(), 0)]);
+  let mut queue = VecDeque::from([PermArray::identity()]);
+  while let Some(permutation) = queue.pop_front() {
+    let d = distance[&permutation];
+    for twist in &twists {
+      let next = PermArray::compose(&permutation, twist);
+      if !distance.contains_key(&next) {
+        distance.insert(next.clone(), d + 1);
+        queue.push_back(next);
+      }
+    }
+  }
+  distance
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // xorshift, to keep scrambles reproducible without a dependency
+  fn scramble(seed: &mut u64, length: usize) -> Vec<Twist> {
+    (0..length)
+      .map(|_| {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        Twist::ALL[(*seed % 8) as usize]
+      })
+      .collect()
+  }
+
+  #[test]
+  fn solved_needs_no_twists() {
+    assert!(Solver::new().solve(&Puzzle::new()).is_empty());
+  }
+
+  #[test]
+  fn solutions_solve_scrambles() {
+    let solver = Solver::new();
+    let mut seed = 0x5eed;
+    for length in 1..30 {
+      let twists = scramble(&mut seed, length);
+      let mut puzzle = Puzzle::new();
+      for twist in &twists {
+        puzzle.twist(*twist);
+      }
+      let solution = solver.solve(&puzzle);
+      assert!(solution.len() <= twists.len());
+      for twist in solution {
+        puzzle.twist(twist);
+      }
+      assert!(puzzle.is_solved(), "{twists:?}");
+    }
+  }
+
+  #[test]
+  fn matches_breadth_first_search() {
+    let solver = Solver::new();
+    let identity = (PermArray::identity(), PermArray::identity());
+    let mut distance = HashMap::from([(identity.clone(), 0)]);
+    let mut queue = VecDeque::from([identity]);
+    let mut checked = 0;
+    while let Some((edges, triangles)) = queue.pop_front() {
+      let d = distance[&(edges.clone(), triangles.clone())];
+      // the first state found at each distance
+      if d == checked {
+        let mut puzzle = Puzzle::new();
+        puzzle.edge_permutation = edges.clone();
+        puzzle.triangle_permutation = triangles.clone();
+        assert_eq!(solver.distance(&puzzle), d);
+        checked += 1;
+      }
+      for (_, twist_edges, twist_triangles) in &solver.twists {
+        let next = (
+          PermArray::compose(&edges, twist_edges),
+          PermArray::compose(&triangles, twist_triangles),
+        );
+        if !distance.contains_key(&next) {
+          distance.insert(next.clone(), d + 1);
+          queue.push_back(next);
+        }
+      }
+    }
+    assert_eq!(distance.len(), 138240);
+    assert_eq!(checked, 11);
+  }
+
+  #[test]
+  fn hints_lead_to_solved() {
+    let solver = Solver::new();
+    let mut puzzle = Puzzle::new();
+    for twist in scramble(&mut 0xabc, 12) {
+      puzzle.twist(twist);
+    }
+    let mut distance = solver.distance(&puzzle);
+    while let Some(twist) = solver.hint(&puzzle) {
+      puzzle.twist(twist);
+      assert_eq!(solver.distance(&puzzle), distance - 1);
+      distance -= 1;
+    }
+    assert!(puzzle.is_solved());
+  }
+
+  #[test]
+  fn search_goes_deeper_each_step() {
+    let solver = Solver::new();
+    let mut puzzle = Puzzle::new();
+    for twist in scramble(&mut 7, 20) {
+      puzzle.twist(twist);
+    }
+    let mut search = solver.start(&puzzle).unwrap();
+    let mut depth = search.depth();
+    let solution = loop {
+      if let Some(solution) = search.step(&solver) {
+        break solution;
+      }
+      assert_eq!(search.depth(), depth + 1);
+      depth += 1;
+    };
+    assert_eq!(solution.len(), depth as usize);
+  }
+
+  #[test]
+  fn rejects_unreachable_states() {
+    let mut puzzle = Puzzle::new();
+    puzzle.triangle_permutation = PermArray::from([1, 0, 2, 3, 4, 5, 6, 7]);
+    assert!(Solver::new().start(&puzzle).is_err());
+  }
+
+  #[test]
+  fn solutions_are_shortest() {
+    let solver = Solver::new();
+    for first in Twist::ALL {
+      let mut puzzle = Puzzle::new();
+      puzzle.twist(first);
+      assert_eq!(solver.distance(&puzzle), 1);
+      for second in Twist::ALL {
+        let mut puzzle = puzzle.clone();
+        puzzle.twist(second);
+        let expected = if second == first.inverse() {
+          0
+        } else if second == first {
+          1
+        } else {
+          2
+        };
+        assert_eq!(solver.distance(&puzzle), expected, "{first:?} {second:?}");
+      }
+    }
+  }
+}
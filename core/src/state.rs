@@ -0,0 +1,735 @@
+use crate::color::ColorScheme;
+use crate::macros::Macro;
+use crate::metric::MoveCount;
+use crate::notation;
+use crate::pattern;
+use crate::puzzle::{Face, Hex, Puzzle, Square};
+use gl_matrix::common::{Mat4, Vec3, PI};
+use gl_matrix::{mat4, vec3};
+use std::borrow::BorrowMut;
+use std::collections::VecDeque;
+
+pub const ANIMATION_DURATION: f32 = 400.0;
+
+// brightness of pieces left out of a highlighted twist
+const HIGHLIGHT_DIM: f32 = 0.35;
+
+// it takes 1.6 seconds to rotate the camera 120 degrees
+pub const CAMERA_SPEED: f32 = (2.0 * std::f32::consts::PI) / (3.0 * 1600.0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Twist {
+  Center { octant: u8 },
+}
+impl Twist {
+  /// Every twist, in the order of `notation::LETTERS` then their inverses.
+  pub const ALL: [Twist; 8] = [
+    Twist::Center { octant: 7 },
+    Twist::Center { octant: 5 },
+    Twist::Center { octant: 4 },
+    Twist::Center { octant: 6 },
+    Twist::Center { octant: 3 },
+    Twist::Center { octant: 1 },
+    Twist::Center { octant: 0 },
+    Twist::Center { octant: 2 },
+  ];
+
+  /// The twist about the same axis in the opposite direction.
+  pub fn inverse(self) -> Self {
+    let Twist::Center { octant } = self;
+    Twist::Center { octant: octant ^ 4 }
+  }
+
+  pub fn positive(&self) -> bool {
+    let Twist::Center { octant } = self;
+    octant & 4 > 0
+  }
+
+  pub fn to_normal(self) -> Vec3 {
+    match self {
+      //Twist::Side { .. } => todo!(),
+      Twist::Center { octant } => {
+        let mut axis = [-1., -1., 1.];
+        if octant & 1 != 0 {
+          axis[0] = 1.;
+        }
+
+        if octant & 2 != 0 {
+          axis[1] = 1.;
+        }
+
+        axis
+      }
+    }
+  }
+
+  pub fn to_matrix(self, frame: f32) -> Mat4 {
+    let mut angle = ((2. * PI) / 3.) * f32::min(1., frame / ANIMATION_DURATION);
+    if !self.positive() {
+      angle *= -1.;
+    }
+
+    let mut matrix = mat4::create();
+    mat4::from_rotation(&mut matrix, angle, &self.to_normal());
+    matrix
+  }
+}
+
+#[derive(Debug)]
+struct Piece {
+  pub normal: Vec3,
+  pub facets: Vec<Facet>,
+}
+
+impl From<Facet> for Piece {
+  fn from(value: Facet) -> Self {
+    Self {
+      normal: value.normal,
+      facets: vec![value],
+    }
+  }
+}
+impl Piece {
+  fn transform(&mut self, matrix: &Mat4) {
+    let mut temp = [0.; 3];
+    vec3::transform_mat4(&mut temp, &self.normal, matrix);
+    self.normal = temp;
+    for facet in self.facets.iter_mut() {
+      facet.transform(matrix);
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct State {
+  pub camera_transform: Mat4,
+  pub camera_axis: Vec3,
+  frame: f32,
+  then: f32,
+  pieces: Vec<Piece>,
+  pub twist_buffer: VecDeque<Twist>,
+  pub color_scheme: ColorScheme,
+  /// The same pieces as permutations, kept in step with completed twists.
+  pub puzzle: Puzzle,
+  /// A suggested twist. Pieces it would move keep their colors, the rest are dimmed.
+  pub highlight: Option<Twist>,
+  /// Twists queued with `queue`, simplified, so `undo` never takes back more than it must.
+  pub history: Vec<Twist>,
+  /// Twists queued since recording started, simplified, see `macros`.
+  pub recording: Option<Vec<Twist>>,
+  pub macros: Vec<Macro>,
+  /// Moves made since the last `reset`.
+  pub moves: MoveCount,
+}
+
+impl Default for State {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl State {
+  pub fn new() -> Self {
+    let mut camera_transform = mat4::create();
+    mat4::identity(&mut camera_transform);
+    let pieces = Self::init_pieces();
+    Self {
+      camera_transform,
+      camera_axis: vec3::create(),
+      frame: 0.0,
+      then: 0.0,
+      twist_buffer: VecDeque::new(),
+      pieces,
+      color_scheme: ColorScheme::default(),
+      puzzle: Puzzle::new(),
+      highlight: None,
+      history: Vec::new(),
+      recording: None,
+      macros: Vec::new(),
+      moves: MoveCount::new(),
+    }
+  }
+
+  /// Step animations forward to the given timestamp.
+  /// Returns whether the camera moved.
+  pub fn advance(&mut self, ms: f32) -> bool {
+    let delta = ms - self.then;
+    self.then = ms;
+    if !self.twist_buffer.is_empty() {
+      self.frame += delta;
+      if self.frame > ANIMATION_DURATION {
+        self.complete_twist();
+      }
+    }
+
+    if self.camera_axis.iter().any(|c| *c != 0.0) {
+      let mut transform = mat4::create();
+      mat4::from_rotation(&mut transform, delta * CAMERA_SPEED, &self.camera_axis);
+      let mut camera = mat4::create();
+      mat4::multiply(&mut camera, &transform, &self.camera_transform);
+      self.camera_transform = camera;
+      true
+    } else {
+      false
+    }
+  }
+
+  /// Apply every queued twist at once, skipping the animations.
+  pub fn finish_twists(&mut self) {
+    while !self.twist_buffer.is_empty() {
+      self.frame = ANIMATION_DURATION;
+      self.complete_twist();
+    }
+  }
+
+  /// Put every piece back where it started and forget the history and move count,
+  /// keeping the camera, colors and macros.
+  pub fn reset(&mut self) {
+    self.pieces = Self::init_pieces();
+    self.puzzle = Puzzle::new();
+    self.twist_buffer.clear();
+    self.history.clear();
+    self.highlight = None;
+    self.moves = MoveCount::new();
+    self.frame = 0.;
+  }
+
+  /// Queue a twist and remember it for `undo`.
+  pub fn queue(&mut self, twist: Twist) {
+    self.twist_buffer.push_back(twist);
+    self.moves.twist(twist);
+    notation::append(&mut self.history, twist);
+    if let Some(recording) = &mut self.recording {
+      notation::append(recording, twist);
+    }
+  }
+
+  /// Queue the inverse of the last twist in the history.
+  /// Returns false when there is nothing left to undo.
+  pub fn undo(&mut self) -> bool {
+    let Some(twist) = self.history.pop() else {
+      return false;
+    };
+    self.twist_buffer.push_back(twist.inverse());
+    self.moves.twist(twist.inverse());
+    if let Some(recording) = &mut self.recording {
+      notation::append(recording, twist.inverse());
+    }
+    true
+  }
+
+  /// The puzzle as it will be once the queued twists are done.
+  pub fn target(&self) -> Puzzle {
+    let mut puzzle = self.puzzle.clone();
+    for twist in &self.twist_buffer {
+      puzzle.twist(*twist);
+    }
+    puzzle
+  }
+
+  fn complete_twist(&mut self) {
+    if let Some(twist) = self.twist_buffer.pop_front() {
+      self.puzzle.twist(twist);
+      let normal = twist.to_normal();
+      let twist = twist.to_matrix(self.frame);
+      for piece in self.pieces.iter_mut() {
+        if vec3::dot(&normal, &piece.normal) > 0. {
+          piece.transform(&twist)
+        }
+      }
+    }
+    self.frame = 0.;
+  }
+
+  fn facets(&self) -> impl Iterator<Item = &Facet> {
+    self.pieces.iter().flat_map(|p| p.facets.iter())
+  }
+
+  fn init_pieces() -> Vec<Piece> {
+    // 8 hexagonal faces
+    // each hexagon is split into 3 trapezoid facets and 1 center triangle
+    // 6 square faces
+    // 4 cross sections
+    let mut pieces = Vec::with_capacity(6 + 8);
+
+    // depth of a square from the center of the puzzle
+    let a = 1.5;
+    // square sidelength
+    let b = 2.0_f32.sqrt() / 2.0;
+    let c = (a + b) / 2.0;
+
+    let mut rot_x = mat4::create();
+    mat4::from_x_rotation(&mut rot_x, PI / 2.0);
+
+    let mut rot_y = mat4::create();
+    mat4::from_y_rotation(&mut rot_y, PI / 2.0);
+    let square1 = Facet {
+      normal: [0.0, 0.0, 1.0],
+      face: Face::Square(Square::Orange),
+      mesh: vec![
+        b, 0., a, //
+        0., b, a, //
+        -b, 0., a, //
+        0., -b, a, //
+      ],
+    };
+
+    let square2 = square1.clone_with(&rot_x, Face::Square(Square::White));
+    let square3 = square2.clone_with(&rot_x, Face::Square(Square::Red));
+    let square4 = square3.clone_with(&rot_x, Face::Square(Square::Yellow));
+    let square5 = square1.clone_with(&rot_y, Face::Square(Square::Blue));
+    let square6 = square3.clone_with(&rot_y, Face::Square(Square::Green));
+
+    let triangle1 = Facet {
+      face: Face::Hex(Hex::White),
+      normal: [1., 1., 1.],
+      mesh: vec![
+        c, 0., c, //
+        0., c, c, //
+        c, c, 0., //
+      ],
+    };
+
+    let triangle2 = triangle1.clone_with(&rot_x, Face::Hex(Hex::Green));
+    let triangle3 = triangle2.clone_with(&rot_x, Face::Hex(Hex::Pink));
+    let triangle4 = triangle3.clone_with(&rot_x, Face::Hex(Hex::Orange));
+    let triangle5 = triangle3.clone_with(&rot_y, Face::Hex(Hex::Yellow));
+    let triangle6 = triangle4.clone_with(&rot_y, Face::Hex(Hex::Blue));
+    let triangle7 = triangle5.clone_with(&rot_y, Face::Hex(Hex::Red));
+    let triangle8 = triangle6.clone_with(&rot_y, Face::Hex(Hex::Silver));
+
+    let trapezoid1a = Facet {
+      face: Face::Hex(Hex::White),
+      normal: [1., 1., 1.],
+      mesh: vec![
+        c, 0., c, //
+        0., c, c, //
+        0., b, a, //
+        b, 0., a, //
+      ],
+    };
+
+    let mut rot_oct1 = mat4::create();
+    mat4::from_rotation(&mut rot_oct1, 2.0 * PI / 3.0, &[1., 1., 1.]);
+    let trapezoid1b = trapezoid1a.clone_with(&rot_oct1, Face::Hex(Hex::White));
+    let trapezoid1c = trapezoid1b.clone_with(&rot_oct1, Face::Hex(Hex::White));
+
+    let trapezoid2a = trapezoid1a.clone_with(&rot_x, Face::Hex(Hex::Green));
+    let trapezoid2b = trapezoid1b.clone_with(&rot_x, Face::Hex(Hex::Green));
+    let trapezoid2c = trapezoid1c.clone_with(&rot_x, Face::Hex(Hex::Green));
+
+    let trapezoid3a = trapezoid2a.clone_with(&rot_x, Face::Hex(Hex::Pink));
+    let trapezoid3b = trapezoid2b.clone_with(&rot_x, Face::Hex(Hex::Pink));
+    let trapezoid3c = trapezoid2c.clone_with(&rot_x, Face::Hex(Hex::Pink));
+
+    let trapezoid4a = trapezoid3a.clone_with(&rot_x, Face::Hex(Hex::Orange));
+    let trapezoid4b = trapezoid3b.clone_with(&rot_x, Face::Hex(Hex::Orange));
+    let trapezoid4c = trapezoid3c.clone_with(&rot_x, Face::Hex(Hex::Orange));
+
+    let trapezoid5a = trapezoid3a.clone_with(&rot_y, Face::Hex(Hex::Yellow));
+    let trapezoid5b = trapezoid3b.clone_with(&rot_y, Face::Hex(Hex::Yellow));
+    let trapezoid5c = trapezoid3c.clone_with(&rot_y, Face::Hex(Hex::Yellow));
+
+    let trapezoid6a = trapezoid4a.clone_with(&rot_y, Face::Hex(Hex::Blue));
+    let trapezoid6b = trapezoid4b.clone_with(&rot_y, Face::Hex(Hex::Blue));
+    let trapezoid6c = trapezoid4c.clone_with(&rot_y, Face::Hex(Hex::Blue));
+
+    let trapezoid7a = trapezoid5a.clone_with(&rot_y, Face::Hex(Hex::Red));
+    let trapezoid7b = trapezoid5b.clone_with(&rot_y, Face::Hex(Hex::Red));
+    let trapezoid7c = trapezoid5c.clone_with(&rot_y, Face::Hex(Hex::Red));
+
+    let trapezoid8a = trapezoid6a.clone_with(&rot_y, Face::Hex(Hex::Silver));
+    let trapezoid8b = trapezoid6b.clone_with(&rot_y, Face::Hex(Hex::Silver));
+    let trapezoid8c = trapezoid6c.clone_with(&rot_y, Face::Hex(Hex::Silver));
+
+    pieces.push(Piece {
+      normal: square1.normal,
+      facets: vec![square1, trapezoid1a, trapezoid2c, trapezoid7a, trapezoid8c],
+    });
+    pieces.push(Piece {
+      normal: square2.normal,
+      facets: vec![square2, trapezoid2a, trapezoid7c, trapezoid3c, trapezoid5c],
+    });
+    pieces.push(Piece {
+      normal: square3.normal,
+      facets: vec![square3, trapezoid3a, trapezoid5b, trapezoid4c, trapezoid6b],
+    });
+    pieces.push(Piece {
+      normal: square4.normal,
+      facets: vec![square4, trapezoid4a, trapezoid6a, trapezoid1c, trapezoid8a],
+    });
+    pieces.push(Piece {
+      normal: square5.normal,
+      facets: vec![square5, trapezoid1b, trapezoid3b, trapezoid2b, trapezoid4b],
+    });
+    pieces.push(Piece {
+      normal: square6.normal,
+      facets: vec![square6, trapezoid5a, trapezoid6c, trapezoid7b, trapezoid8b],
+    });
+
+    pieces.push(triangle1.into());
+    pieces.push(triangle2.into());
+    pieces.push(triangle3.into());
+    pieces.push(triangle4.into());
+    pieces.push(triangle5.into());
+    pieces.push(triangle6.into());
+    pieces.push(triangle7.into());
+    pieces.push(triangle8.into());
+    pieces
+  }
+
+  pub fn get_vertex_count(&self) -> u32 {
+    self.facets().map(|f| f.get_vertex_count()).sum()
+  }
+
+  pub fn get_index_count(&self) -> u32 {
+    self.facets().map(|f| f.get_index_count()).sum()
+  }
+
+  pub fn get_vertex_indices(&self) -> Vec<u32> {
+    let mut array = Vec::with_capacity(self.get_index_count() as usize);
+    let mut total: u32 = 0;
+    for facet in self.facets() {
+      let count = facet.get_vertex_count();
+      for i in 0..(count - 2) {
+        array.push(total);
+        array.push(total + i + 1);
+        array.push(total + i + 2);
+      }
+      total += count;
+    }
+    array
+  }
+
+  pub fn get_vertex_colors(&self) -> Vec<f32> {
+    // n vertices times 4 rgba values
+    let mut array = Vec::with_capacity(4 * self.get_vertex_count() as usize);
+    for piece in self.pieces.iter() {
+      let dim = self
+        .highlight
+        .is_some_and(|twist| vec3::dot(&twist.to_normal(), &piece.normal) <= 0.);
+      let brightness = if dim { HIGHLIGHT_DIM } else { 1. };
+      for facet in piece.facets.iter() {
+        let color = self.color_scheme.color(facet.face);
+        for _ in 0..facet.get_vertex_count() {
+          array.push(brightness * color.red());
+          array.push(brightness * color.green());
+          array.push(brightness * color.blue());
+          array.push(color.alpha());
+        }
+      }
+    }
+
+    array
+  }
+
+  // Texture coordinates in the plane of each facet, relative to its first vertex.
+  // They only depend on the facet's shape, so they survive twists unchanged.
+  pub fn get_vertex_uvs(&self) -> Vec<f32> {
+    // pattern repeats per unit length
+    let scale = 2.0;
+    let mut array = Vec::with_capacity(2 * self.get_vertex_count() as usize);
+    for facet in self.facets() {
+      let origin: Vec3 = facet.mesh[0..3].try_into().unwrap();
+      let mut tangent = vec3::create();
+      vec3::subtract(&mut tangent, &facet.mesh[3..6].try_into().unwrap(), &origin);
+      let edge = tangent;
+      vec3::normalize(&mut tangent, &edge);
+      let mut normal = vec3::create();
+      vec3::normalize(&mut normal, &facet.normal);
+      let mut bitangent = vec3::create();
+      vec3::cross(&mut bitangent, &normal, &tangent);
+
+      for vertex in facet.mesh.chunks_exact(3) {
+        let mut offset = vec3::create();
+        vec3::subtract(&mut offset, &vertex.try_into().unwrap(), &origin);
+        array.push(scale * vec3::dot(&offset, &tangent));
+        array.push(scale * vec3::dot(&offset, &bitangent));
+      }
+    }
+    array
+  }
+
+  pub fn get_vertex_patterns(&self) -> Vec<f32> {
+    let mut array = Vec::with_capacity(self.get_vertex_count() as usize);
+    for facet in self.facets() {
+      let pattern = pattern::pattern(facet.face) as f32;
+      for _ in 0..facet.get_vertex_count() {
+        array.push(pattern);
+      }
+    }
+    array
+  }
+
+  pub fn get_vertex_positions(&self) -> Vec<f32> {
+    let mut vector = vec![0.0; self.get_vertex_count() as usize * 3];
+    let mut offset = 0;
+
+    // slow path
+    if let Some(twist) = self.twist_buffer.front() {
+      let normal = twist.to_normal();
+      for piece in self.pieces.iter() {
+        for facet in piece.facets.iter() {
+          let data: &mut [f32] = vector[offset..(offset + facet.mesh.len())].borrow_mut();
+          data.copy_from_slice(&facet.mesh);
+
+          if vec3::dot(&normal, &piece.normal) > 0. {
+            let mut mesh = Mesh { data };
+            mesh.transform(&twist.to_matrix(self.frame));
+          }
+          offset += facet.mesh.len();
+        }
+      }
+    }
+    // fast path
+    else {
+      for facet in self.facets() {
+        vector[offset..(offset + facet.mesh.len())].copy_from_slice(&facet.mesh);
+        offset += facet.mesh.len();
+      }
+    }
+
+    vector
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Facet {
+  mesh: Vec<f32>,
+  normal: Vec3,
+  face: Face,
+}
+
+impl Facet {
+  fn clone_with(&self, matrix: &Mat4, face: Face) -> Facet {
+    let mut facet = self.clone();
+    facet.transform(matrix);
+    facet.face = face;
+    facet
+  }
+  fn transform(&mut self, matrix: &Mat4) {
+    let mut temp = [0.0_f32; 3];
+    vec3::transform_mat4(&mut temp, &self.normal, matrix);
+    self.normal = temp;
+
+    let n = self.mesh.len() / 3;
+
+    for i in 0..n {
+      let slice: &mut [f32] = self.mesh[3 * i..3 * i + 3].borrow_mut();
+      vec3::transform_mat4(&mut temp, &slice.try_into().unwrap(), matrix);
+      slice.copy_from_slice(temp.as_slice());
+    }
+  }
+
+  fn get_vertex_count(&self) -> u32 {
+    self.mesh.len() as u32 / 3
+  }
+
+  fn get_index_count(&self) -> u32 {
+    3 * (self.get_vertex_count() - 2)
+  }
+}
+
+struct Mesh<'a> {
+  data: &'a mut [f32],
+}
+
+impl<'a> Mesh<'a> {
+  fn transform(&mut self, matrix: &Mat4) {
+    let mut temp = [0.0_f32; 3];
+
+    for i in 0..self.data.len() / 3 {
+      // safe because the types guarantee the array size is a multiple of 3;
+      let slice: &mut [f32] = self.data[3 * i..3 * (i + 1)].borrow_mut();
+      vec3::transform_mat4(&mut temp, &slice.try_into().unwrap(), matrix);
+      slice.copy_from_slice(temp.as_slice());
+    }
+  }
+}
+
+/*
+struct Mesh<const N: usize>
+where
+  [f32; 3 * N]: Sized,
+{
+  data: [f32; 3 * N],
+}
+
+impl<const N: usize> Mesh<N>
+where
+  [f32; 3 * N]: Sized,
+{
+  fn transform(&mut self, matrix: &Mat4) {
+    let mut temp = [0.0_f32; 3];
+
+    for i in 0..N {
+      // safe because the types guarantee the array size is a multiple of 3;
+      let slice: &mut [f32] = self.data[3 * i..3 * (i + 1)].borrow_mut();
+      vec3::transform_mat4(&mut temp, &slice.try_into().unwrap(), matrix);
+      slice.copy_from_slice(temp.as_slice());
+    }
+  }
+}
+*/
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::puzzle::{EdgeFacet, Puzzle, SquareFacet, TriangleFacet};
+
+  fn settle(state: &mut State) {
+    let mut ms = 0.;
+    while !state.twist_buffer.is_empty() {
+      ms += ANIMATION_DURATION + 1.;
+      state.advance(ms);
+    }
+  }
+
+  fn assert_close(a: &[f32], b: &[f32]) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter().zip(b) {
+      assert!((x - y).abs() < 1e-4, "{x} != {y}");
+    }
+  }
+
+  /// The slot a facet sits in, read from its geometry, paired with its color.
+  fn slot(facet: &Facet) -> (String, Face) {
+    let n = facet.mesh.len() / 3;
+    let mut centroid = [0.; 3];
+    for vertex in facet.mesh.chunks(3) {
+      for i in 0..3 {
+        centroid[i] += vertex[i] / n as f32;
+      }
+    }
+    let normal = facet.normal.map(|c| c.round() as i32);
+    let square = || {
+      let axis = (0..3)
+        .max_by(|a, b| centroid[*a].abs().total_cmp(&centroid[*b].abs()))
+        .unwrap();
+      let mut normal = [0; 3];
+      normal[axis] = centroid[axis].signum() as i32;
+      Square::from_normal(normal).unwrap()
+    };
+    let slot = match (Hex::from_normal(normal), n) {
+      (None, _) => format!("{:?}", SquareFacet(square())),
+      (Some(hex), 3) => format!("{:?}", TriangleFacet(hex)),
+      (Some(hex), _) => format!("{:?}", EdgeFacet(square(), hex)),
+    };
+    (slot, facet.face)
+  }
+
+  #[test]
+  fn twists_match_puzzle_model() {
+    let twists = [7, 2, 5, 5, 0, 6, 3, 4, 1].map(|octant| Twist::Center { octant });
+    let mut state = State::new();
+    let mut puzzle = Puzzle::new();
+    for twist in twists {
+      state.twist_buffer.push_back(twist);
+      puzzle.twist(twist);
+    }
+    settle(&mut state);
+
+    let mut actual: Vec<_> = state.facets().map(slot).collect();
+    let mut expected = Vec::new();
+    for (i, s) in puzzle.squares.iter().enumerate() {
+      expected.push((format!("{s:?}"), puzzle.square_at(i).face()));
+    }
+    for (i, t) in puzzle.triangles.iter().enumerate() {
+      expected.push((format!("{t:?}"), puzzle.triangle_at(i).face()));
+    }
+    for (i, e) in puzzle.edges.iter().enumerate() {
+      expected.push((format!("{e:?}"), puzzle.edge_at(i).face()));
+    }
+    actual.sort_by_key(|(slot, _)| slot.clone());
+    expected.sort_by_key(|(slot, _)| slot.clone());
+    assert_eq!(actual, expected);
+  }
+
+  #[test]
+  fn highlight_dims_pieces_that_stay() {
+    let mut state = State::new();
+    let colors = state.get_vertex_colors();
+    state.highlight = Some(Twist::Center { octant: 7 });
+    let highlighted = state.get_vertex_colors();
+    let dimmed = colors
+      .chunks(4)
+      .zip(highlighted.chunks(4))
+      .filter(|(a, b)| a != b)
+      .count();
+    assert!(dimmed > 0 && dimmed < colors.len() / 4);
+  }
+
+  #[test]
+  fn three_twists_are_identity() {
+    let solved = State::new().get_vertex_positions();
+    for octant in 0..8 {
+      let mut state = State::new();
+      for _ in 0..3 {
+        state.twist_buffer.push_back(Twist::Center { octant });
+      }
+      settle(&mut state);
+      assert_close(&state.get_vertex_positions(), &solved);
+    }
+  }
+
+  #[test]
+  fn twist_is_undone_by_inverse() {
+    let solved = State::new();
+    let mut state = State::new();
+    state.twist_buffer.push_back(Twist::Center { octant: 7 });
+    state.twist_buffer.push_back(Twist::Center { octant: 3 });
+    settle(&mut state);
+    assert_close(
+      &state.get_vertex_positions(),
+      &solved.get_vertex_positions(),
+    );
+    assert_eq!(state.get_vertex_colors(), solved.get_vertex_colors());
+  }
+
+  #[test]
+  fn undo_takes_back_the_simplified_history() {
+    let mut state = State::new();
+    for octant in [7, 7, 5, 5, 1] {
+      state.queue(Twist::Center { octant });
+    }
+    // H H J J J' is H' J
+    assert_eq!(
+      state.history,
+      [Twist::Center { octant: 3 }, Twist::Center { octant: 5 }]
+    );
+    assert!(state.undo());
+    assert!(state.undo());
+    assert!(!state.undo());
+    settle(&mut state);
+    assert!(state.puzzle.is_solved());
+  }
+
+  #[test]
+  fn reset_returns_to_solved() {
+    let solved = State::new();
+    let mut state = State::new();
+    state.queue(Twist::Center { octant: 7 });
+    state.queue(Twist::Center { octant: 5 });
+    state.advance(ANIMATION_DURATION + 1.);
+    state.reset();
+    assert!(state.puzzle.is_solved());
+    assert!(state.twist_buffer.is_empty() && state.history.is_empty());
+    assert_close(
+      &state.get_vertex_positions(),
+      &solved.get_vertex_positions(),
+    );
+  }
+
+  #[test]
+  fn twist_moves_pieces() {
+    let solved = State::new().get_vertex_positions();
+    let mut state = State::new();
+    state.twist_buffer.push_back(Twist::Center { octant: 7 });
+    settle(&mut state);
+    let positions = state.get_vertex_positions();
+    assert!(positions
+      .iter()
+      .zip(&solved)
+      .any(|(a, b)| (a - b).abs() > 1e-3));
+  }
+}
@@ -0,0 +1,115 @@
+use crate::color::Color;
+use crate::render::{self, Backend, Background, Scene};
+use crate::software::Software;
+use crate::state::State;
+use gl_matrix::common::{Mat4, PI};
+use gl_matrix::mat4;
+use std::fmt::Write;
+
+// drawn behind the puzzle and left to the terminal's own background
+const KEY: [u8; 3] = [1, 2, 3];
+
+/// Draw the state twice, looking at the white hexagon and at the yellow one,
+/// so every face shows in one of the views.
+/// Each view is `size` columns wide and half as many rows tall.
+pub fn render(state: &State, size: usize, patterns: bool) -> String {
+  let scene = Scene {
+    background: Background::Solid(Color::rgb(KEY[0], KEY[1], KEY[2])),
+    patterns,
+    ground_shadow: false,
+  };
+  let views = [front(), back()].map(|view| {
+    let mut camera = mat4::create();
+    mat4::multiply(&mut camera, &view, &state.camera_transform);
+    let mut backend = Software::new(size, size);
+    render::upload(&mut backend, state);
+    backend.set_camera(&camera);
+    backend.draw(&scene).unwrap();
+    backend
+  });
+
+  let mut out = String::new();
+  for row in (0..size).step_by(2) {
+    for backend in &views {
+      write_row(&mut out, backend, row);
+      out.push_str("  ");
+    }
+    out.push('\n');
+  }
+  out
+}
+
+// turns the white hexagon, (1, 1, 1), toward the viewer
+fn front() -> Mat4 {
+  let mut view = mat4::create();
+  let angle = (1. / 3.0_f32.sqrt()).acos();
+  mat4::from_rotation(&mut view, angle, &[-1., 1., 0.]);
+  view
+}
+
+fn back() -> Mat4 {
+  let mut half_turn = mat4::create();
+  mat4::from_y_rotation(&mut half_turn, PI);
+  let mut view = mat4::create();
+  mat4::multiply(&mut view, &half_turn, &front());
+  view
+}
+
+/// Two rows of pixels as one line of `▀`, the upper pixel in the foreground color
+/// and the lower one in the background color.
+fn write_row(out: &mut String, backend: &Software, row: usize) {
+  let pixel = |x: usize, y: usize| {
+    let i = 4 * (y * backend.width() + x);
+    let p = &backend.pixels()[i..i + 3];
+    [p[0], p[1], p[2]]
+  };
+
+  for x in 0..backend.width() {
+    let top = pixel(x, row);
+    let bottom = if row + 1 < backend.height() {
+      pixel(x, row + 1)
+    } else {
+      KEY
+    };
+    match (top == KEY, bottom == KEY) {
+      (true, true) => out.push(' '),
+      (false, true) => {
+        let _ = write!(out, "\x1b[38;2;{}m▀\x1b[0m", rgb(top));
+      }
+      (true, false) => {
+        let _ = write!(out, "\x1b[38;2;{}m▄\x1b[0m", rgb(bottom));
+      }
+      (false, false) => {
+        let _ = write!(out, "\x1b[38;2;{};48;2;{}m▀\x1b[0m", rgb(top), rgb(bottom));
+      }
+    }
+  }
+}
+
+fn rgb([red, green, blue]: [u8; 3]) -> String {
+  format!("{red};{green};{blue}")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::color::ColorScheme;
+  use crate::puzzle::{Face, Hex, Square};
+
+  #[test]
+  fn every_face_shows() {
+    let art = render(&State::new(), 40, false);
+    assert_eq!(art.lines().count(), 20);
+    assert!(art.lines().next().unwrap().trim().is_empty());
+    let scheme = ColorScheme::default();
+    let faces = Square::ALL
+      .map(Face::Square)
+      .into_iter()
+      .chain(Hex::ALL.map(Face::Hex));
+    for face in faces {
+      let color = scheme.color(face);
+      let color = rgb([color.red(), color.green(), color.blue()].map(|c| (c * 255.).round() as u8));
+      assert!(art.contains(&color), "{face:?} is not drawn");
+    }
+  }
+}
@@ -0,0 +1,232 @@
+use crate::metric::MoveCount;
+use crate::state::State;
+use std::fmt::Write;
+
+/// Time allowed to look over a scramble before the clock starts anyway.
+pub const INSPECTION: f32 = 15000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+  /// No solve under way.
+  Idle,
+  /// Counting down the inspection time, from the timestamp it began.
+  Inspecting {
+    since: f32,
+  },
+  /// Waiting for the first twist without inspection.
+  Ready,
+  Solving {
+    since: f32,
+  },
+  Solved {
+    time: f32,
+  },
+}
+
+/// A speed solve clock driven by the timestamps `State::advance` sees.
+/// Timing starts with the first twist, or once inspection runs out,
+/// and stops when the puzzle comes back to solved.
+#[derive(Clone, Debug)]
+pub struct Timer {
+  pub phase: Phase,
+  /// Finished solves this session, oldest first.
+  pub solves: Vec<Solve>,
+  now: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Solve {
+  /// Milliseconds from the start of the clock to solved.
+  pub time: f32,
+  pub moves: MoveCount,
+}
+
+/// Session averages in milliseconds, missing until there are enough solves.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+  pub best: Option<f32>,
+  pub mean: Option<f32>,
+  /// The last five solves without their best and worst.
+  pub ao5: Option<f32>,
+  pub ao12: Option<f32>,
+  /// The fewest twists any solve took.
+  pub fewest_twists: Option<usize>,
+}
+
+impl Default for Timer {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Timer {
+  pub fn new() -> Self {
+    Self {
+      phase: Phase::Idle,
+      solves: Vec::new(),
+      now: 0.,
+    }
+  }
+
+  /// Get ready for a solve of a freshly scrambled puzzle.
+  /// A solve still running is abandoned and not counted.
+  pub fn arm(&mut self, inspection: bool) {
+    self.phase = if inspection {
+      Phase::Inspecting { since: self.now }
+    } else {
+      Phase::Ready
+    };
+  }
+
+  /// Stop without counting the solve.
+  pub fn cancel(&mut self) {
+    self.phase = Phase::Idle;
+  }
+
+  /// Follow the state at the timestamp of a frame.
+  pub fn update(&mut self, ms: f32, state: &State) {
+    self.now = ms;
+    let twisting = !state.twist_buffer.is_empty();
+    match self.phase {
+      Phase::Inspecting { since } if twisting || ms - since >= INSPECTION => {
+        let start = if twisting { ms } else { since + INSPECTION };
+        self.phase = Phase::Solving { since: start };
+      }
+      Phase::Ready if twisting => self.phase = Phase::Solving { since: ms },
+      Phase::Solving { since } if !twisting && state.puzzle.is_solved() => {
+        let time = ms - since;
+        self.solves.push(Solve {
+          time,
+          moves: state.moves,
+        });
+        self.phase = Phase::Solved { time };
+      }
+      _ => {}
+    }
+  }
+
+  /// What a display shows: inspection time left, time so far, or the final time.
+  pub fn display(&self) -> f32 {
+    match self.phase {
+      Phase::Idle | Phase::Ready => 0.,
+      Phase::Inspecting { since } => (INSPECTION - (self.now - since)).max(0.),
+      Phase::Solving { since } => self.now - since,
+      Phase::Solved { time } => time,
+    }
+  }
+
+  pub fn stats(&self) -> Stats {
+    let times: Vec<_> = self.solves.iter().map(|s| s.time).collect();
+    Stats {
+      best: times.iter().copied().reduce(f32::min),
+      mean: (!times.is_empty()).then(|| times.iter().sum::<f32>() / times.len() as f32),
+      ao5: average(&times, 5),
+      ao12: average(&times, 12),
+      fewest_twists: self.solves.iter().map(|s| s.moves.twists).min(),
+    }
+  }
+
+  /// The session as CSV, one solve per line with its time in seconds and its move counts.
+  pub fn export(&self) -> String {
+    let mut out = String::from("time,twists,turns,presses\n");
+    for solve in &self.solves {
+      let moves = solve.moves;
+      let _ = writeln!(
+        out,
+        "{:.2},{},{},{}",
+        solve.time / 1000.,
+        moves.twists,
+        moves.turns,
+        moves.presses
+      );
+    }
+    out
+  }
+}
+
+// the mean of the last `n` times, leaving out the best and the worst
+fn average(times: &[f32], n: usize) -> Option<f32> {
+  let mut last = times.get(times.len().checked_sub(n)?..)?.to_vec();
+  last.sort_by(f32::total_cmp);
+  let middle = &last[1..n - 1];
+  Some(middle.iter().sum::<f32>() / middle.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::state::{Twist, ANIMATION_DURATION};
+
+  // scramble with one twist, then solve it at `at`
+  fn solve(timer: &mut Timer, state: &mut State, start: f32, at: f32) {
+    state.twist_buffer.push_back(Twist::ALL[0]);
+    state.finish_twists();
+    timer.update(start, state);
+    timer.arm(false);
+    timer.update(start, state);
+    state.queue(Twist::ALL[0].inverse());
+    timer.update(at, state);
+    state.advance(at);
+    state.advance(at + ANIMATION_DURATION + 1.);
+    timer.update(at + ANIMATION_DURATION + 1., state);
+  }
+
+  #[test]
+  fn times_from_first_twist_to_solved() {
+    let mut timer = Timer::new();
+    let mut state = State::new();
+    solve(&mut timer, &mut state, 1000., 3000.);
+    assert_eq!(
+      timer.phase,
+      Phase::Solved {
+        time: ANIMATION_DURATION + 1.
+      }
+    );
+    assert_eq!(timer.solves.len(), 1);
+    assert_eq!(timer.solves[0].moves.twists, 1);
+    assert_eq!(timer.export(), "time,twists,turns,presses\n0.40,1,1,0\n");
+  }
+
+  #[test]
+  fn inspection_runs_out() {
+    let mut timer = Timer::new();
+    let state = State::new();
+    timer.update(500., &state);
+    timer.arm(true);
+    timer.update(10500., &state);
+    assert_eq!(timer.display(), 5000.);
+    timer.update(16000., &state);
+    assert_eq!(timer.phase, Phase::Solving { since: 15500. });
+    assert_eq!(timer.display(), 500.);
+  }
+
+  #[test]
+  fn waits_while_solved_before_the_first_twist() {
+    let mut timer = Timer::new();
+    let state = State::new();
+    timer.arm(false);
+    timer.update(100., &state);
+    assert_eq!(timer.phase, Phase::Ready);
+  }
+
+  #[test]
+  fn averages_drop_best_and_worst() {
+    let mut timer = Timer::new();
+    assert_eq!(timer.stats(), Stats::default());
+    let solve = |time| Solve {
+      time,
+      moves: MoveCount::new(),
+    };
+    timer.solves = [9000., 5000., 7000., 6000., 8000.].map(solve).to_vec();
+    let stats = timer.stats();
+    assert_eq!(stats.best, Some(5000.));
+    assert_eq!(stats.mean, Some(7000.));
+    assert_eq!(stats.ao5, Some(7000.));
+    assert_eq!(stats.ao12, None);
+
+    timer.solves.push(solve(1000.));
+    assert_eq!(timer.stats().ao5, Some(6000.));
+    timer.solves.extend([1000.; 6].map(solve));
+    assert_eq!(timer.stats().ao12, Some(3200.));
+  }
+}
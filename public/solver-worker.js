@@ -0,0 +1,60 @@
+// Runs the optimal solver off the main thread.
+//
+// Requests:
+//   { type: "solve", id, state }    state is `puzzle_state()` from the main module
+//   { type: "cancel", id }
+// Responses:
+//   { type: "ready" }               the pruning tables are built
+//   { type: "progress", id, depth } no solution is shorter than `depth`
+//   { type: "result", id, moves }   a shortest solution in move notation
+//   { type: "cancelled", id }
+//   { type: "error", id, message }
+import init, { prepare, Job } from "./solver/puzzle_worker.js";
+
+const jobs = new Map();
+const ready = init().then(() => {
+  prepare();
+  postMessage({ type: "ready" });
+});
+
+onmessage = async ({ data }) => {
+  await ready;
+  switch (data.type) {
+    case "solve": {
+      try {
+        jobs.set(data.id, new Job(data.state));
+      } catch (error) {
+        postMessage({ type: "error", id: data.id, message: String(error) });
+        return;
+      }
+      run(data.id);
+      break;
+    }
+    case "cancel": {
+      const job = jobs.get(data.id);
+      if (job) {
+        jobs.delete(data.id);
+        job.free();
+        postMessage({ type: "cancelled", id: data.id });
+      }
+      break;
+    }
+  }
+};
+
+// One depth per task, so cancel messages get a turn in between.
+function run(id) {
+  const job = jobs.get(id);
+  if (!job) {
+    return;
+  }
+  const moves = job.step();
+  if (moves !== undefined) {
+    jobs.delete(id);
+    job.free();
+    postMessage({ type: "result", id, moves });
+  } else {
+    postMessage({ type: "progress", id, depth: job.depth() });
+    setTimeout(() => run(id));
+  }
+}
@@ -1,3 +1 @@
-// pub mod permutation;
-// pub mod puzzle;
 pub mod webgl;
@@ -1,13 +1,25 @@
-use gl_matrix::common::{Mat4, Vec3, PI};
-use gl_matrix::{mat4, vec3};
-use std::borrow::BorrowMut;
-use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use gl_matrix::common::Mat4;
+use gl_matrix::mat4;
+use puzzle_core::color::{Color, ColorScheme};
+use puzzle_core::effect;
+use puzzle_core::input::{self, Command, Keymap};
+use puzzle_core::macros;
+use puzzle_core::notation;
+use puzzle_core::pattern;
+use puzzle_core::persist::Fields;
+use puzzle_core::render::{self, Backend, Background, Scene};
+use puzzle_core::scramble;
+use puzzle_core::share;
+use puzzle_core::solver::Solver;
+use puzzle_core::state::State;
+use puzzle_core::timer::{Phase, Timer};
+use std::cell::{Cell, RefCell};
 use wasm_bindgen::prelude::*;
 use web_sys::js_sys::{Float32Array, Uint32Array};
 use web_sys::{
-  console, window, HtmlCanvasElement, HtmlElement, KeyboardEvent, WebGlProgram,
-  WebGlRenderingContext, WebGlShader,
+  console, window, HtmlCanvasElement, HtmlElement, KeyboardEvent, Storage, WebGlBuffer,
+  WebGlContextAttributes, WebGlFramebuffer, WebGlProgram, WebGlRenderbuffer, WebGlRenderingContext,
+  WebGlShader, WebGlTexture,
 };
 
 // todo
@@ -15,33 +27,29 @@ use web_sys::{
 // animation
 thread_local! {
   static STATE: RefCell<State> = init_state();
-  static KEYMAP: Keymap = init_keymap();
+  static KEYMAP: RefCell<Keymap> = RefCell::new(input::default_keymap());
   static PROJECTION: Mat4 = init_projection();
+  static SETTINGS: Cell<RenderSettings> = Cell::new(RenderSettings::default());
+  static PIPELINE: RefCell<Option<Pipeline>> = const { RefCell::new(None) };
+  static BACKGROUND: Cell<Background> = const { Cell::new(Background::DEFAULT) };
+  static COLOR_SCHEMES: RefCell<Vec<ColorScheme>> = RefCell::new(ColorScheme::builtin());
+  // Full solves belong to the worker, see `public/solver-worker.js`.
+  // Hints, scrambles and restoring a save still search here, each once per click or load:
+  // the tables take about a millisecond to build and most states solve in under one,
+  // with the few that need 10 twists taking tens of milliseconds.
+  static SOLVER: Solver = Solver::new();
+  static TIMER: RefCell<Timer> = RefCell::new(Timer::new());
+  // what local storage holds, and the frame it was last compared at
+  static SAVED: RefCell<String> = const { RefCell::new(String::new()) };
+  static LAST_SAVE: Cell<f32> = const { Cell::new(0.) };
+  // why the link the page was opened with couldn't be shown
+  static LINK_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
 }
 
 fn init_state() -> RefCell<State> {
   RefCell::new(State::new())
 }
 
-fn init_keymap() -> Keymap {
-  HashMap::from([
-    ("w", Command::camera(Orientation::Negative, Axis::X)),
-    ("a", Command::camera(Orientation::Negative, Axis::Y)),
-    ("s", Command::camera(Orientation::Positive, Axis::X)),
-    ("d", Command::camera(Orientation::Positive, Axis::Y)),
-    ("q", Command::camera(Orientation::Positive, Axis::Z)),
-    ("e", Command::camera(Orientation::Negative, Axis::Z)),
-    ("h", Command::twist(true, true, true)),
-    ("j", Command::twist(true, false, true)),
-    ("k", Command::twist(false, false, true)),
-    ("l", Command::twist(false, true, true)),
-    ("y", Command::twist(true, true, false)),
-    ("u", Command::twist(true, false, false)),
-    ("i", Command::twist(false, false, false)),
-    ("o", Command::twist(false, true, false)),
-  ])
-}
-
 fn init_projection() -> Mat4 {
   let gl = webgl_context().unwrap();
   let mut m = mat4::create();
@@ -51,178 +59,791 @@ fn init_projection() -> Mat4 {
 
 type Result<T, E = JsValue> = std::result::Result<T, E>;
 
-type Keymap = HashMap<&'static str, Command>;
-enum Axis {
-  X,
-  Y,
-  Z,
-}
-enum Orientation {
-  Positive,
-  Negative,
-}
-enum Command {
-  Camera {
-    axis: Axis,
-    orientation: Orientation,
-  },
-  Twist {
-    // bit pattern.
-    // first bit is the x axis,
-    // second bit is the y axis,
-    // third bit is orientation
-    octant: u8,
-  },
-}
-impl Command {
-  fn camera(orientation: Orientation, axis: Axis) -> Self {
-    Self::Camera { orientation, axis }
-  }
-
-  fn twist(x: bool, y: bool, z: bool) -> Self {
-    let mut octant = 0;
-    if x {
-      octant |= 1
-    }
-    if y {
-      octant |= 2
-    }
-    if z {
-      octant |= 4
+/// How jagged facet edges are smoothed out.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Antialiasing {
+  Off,
+  /// Multisampling, via the `antialias` attribute of the webgl context.
+  /// Browsers may ignore the attribute, and it can only be set when a context is created,
+  /// so switching to or from it replaces the canvas. A WebGL2 multisampled renderbuffer
+  /// resolved with `blitFramebuffer` would avoid both, but every draw call here goes
+  /// through the WebGL1 `WebGlRenderingContext`, so that path isn't implemented.
+  Msaa,
+  /// A post processing pass over the rendered frame.
+  /// Cheaper than multisampling, but blurs the image slightly.
+  Fxaa,
+}
+
+/// Render quality, adjustable from the page via `set_render_settings`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+  pub antialiasing: Antialiasing,
+  /// Size of the drawing buffer relative to the canvas's css size.
+  /// Values below 1 trade sharpness for speed on low end devices.
+  pub resolution_scale: f32,
+  /// Overlay a pattern on each facet, so colors can be told apart without relying on hue.
+  pub patterns: bool,
+  /// Darken the floor beneath the puzzle.
+  pub ground_shadow: bool,
+}
+
+#[wasm_bindgen]
+impl RenderSettings {
+  #[wasm_bindgen(constructor)]
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Default for RenderSettings {
+  fn default() -> Self {
+    Self {
+      antialiasing: Antialiasing::Msaa,
+      resolution_scale: 1.0,
+      patterns: false,
+      ground_shadow: false,
     }
-    Self::Twist { octant }
   }
 }
 
-const ANIMATION_DURATION: f32 = 400.0;
+#[wasm_bindgen]
+pub fn render_settings() -> RenderSettings {
+  SETTINGS.get()
+}
 
-// it takes 1.6 seconds to rotate the camera 120 degrees
-const CAMERA_SPEED: f32 = (2.0 * std::f32::consts::PI) / (3.0 * 1600.0);
+#[wasm_bindgen]
+pub fn set_render_settings(settings: RenderSettings) -> Result<()> {
+  if !settings.resolution_scale.is_finite() || settings.resolution_scale <= 0.0 {
+    return Err("resolution scale must be positive".into());
+  }
+  let previous = SETTINGS.replace(settings);
+
+  // Context attributes are fixed once a context is created,
+  // so toggling multisampling means starting over with a fresh canvas.
+  let msaa = |s: RenderSettings| s.antialiasing == Antialiasing::Msaa;
+  if msaa(previous) != msaa(settings) {
+    replace_canvas()?;
+    init_gl()?;
+  }
+  Ok(())
+}
 
+/// Colors are css hex strings like `#ff8800`.
 #[wasm_bindgen]
-pub fn render(ms: f32) -> Result<()> {
-  let gl = webgl_context()?;
+pub fn set_solid_background(color: &str) -> Result<()> {
+  BACKGROUND.set(Background::Solid(Color::from_hex(color)?));
+  Ok(())
+}
 
-  STATE.with_borrow_mut(|p| {
-    let delta = ms - p.then;
-    p.then = ms;
-    if p.twist_buffer.len() > 0 {
-      p.frame += delta;
-      if p.frame > ANIMATION_DURATION {
-        p.complete_twist();
+#[wasm_bindgen]
+pub fn set_gradient_background(top: &str, bottom: &str) -> Result<()> {
+  BACKGROUND.set(Background::Gradient {
+    top: Color::from_hex(top)?,
+    bottom: Color::from_hex(bottom)?,
+  });
+  Ok(())
+}
+
+#[wasm_bindgen]
+pub fn set_environment_background() {
+  BACKGROUND.set(Background::Environment);
+}
+
+#[wasm_bindgen]
+pub fn render(ms: f32) -> Result<()> {
+  let settings = SETTINGS.get();
+  let scene = Scene {
+    background: BACKGROUND.get(),
+    patterns: settings.patterns,
+    ground_shadow: settings.ground_shadow,
+  };
+  with_backend(|backend| {
+    STATE.with_borrow_mut(|p| {
+      if p.advance(ms) {
+        backend.set_camera(&p.camera_transform);
       }
-    }
+      TIMER.with_borrow_mut(|timer| timer.update(ms, p));
+      if ms - LAST_SAVE.get() >= SAVE_INTERVAL {
+        LAST_SAVE.set(ms);
+        // rendering goes on without storage
+        let _ = save_state(p);
+      }
+      // which pieces a hint covers changes as queued twists finish
+      if p.highlight.is_some() {
+        backend.set_vertex_colors(&p.get_vertex_colors());
+      }
+      backend.set_vertex_positions(&p.get_vertex_positions());
+      backend.draw(&scene)
+    })
+  })
+}
+
+#[wasm_bindgen]
+pub fn on_key_down(event: &KeyboardEvent) {
+  console::log_2(&JsValue::from("keydown"), &JsValue::from(event));
+  let Some(command) = KEYMAP.with_borrow(|keymap| keymap.get(event.key().as_str()).copied()) else {
+    return;
+  };
+  let cleared_hint = STATE.with_borrow_mut(|state| {
+    let hinted = state.highlight.is_some();
+    state.press(&command);
+    hinted && state.highlight.is_none()
+  });
+  // nothing to report errors to from a keyboard handler
+  if cleared_hint {
+    let _ = refresh_colors();
+  }
+  if command == Command::Record {
+    let _ = save_macros();
+  }
+}
 
-    if p.camera_axis.iter().any(|c| *c != 0.0) {
-      let mut transform = mat4::create();
-      mat4::from_rotation(&mut transform, delta * CAMERA_SPEED, &p.camera_axis);
-      let mut camera = mat4::create();
-      mat4::multiply(&mut camera, &transform, &p.camera_transform);
-      p.camera_transform = camera;
-
-      PROJECTION.with(|projection| {
-        mat4::multiply(&mut transform, projection, &p.camera_transform);
-        set_transform_matrix(&gl, &transform);
-      });
+#[wasm_bindgen]
+pub fn on_key_up(event: &KeyboardEvent) {
+  KEYMAP.with_borrow(|keymap| {
+    if let Some(command) = keymap.get(event.key().as_str()) {
+      STATE.with_borrow_mut(|state| state.release(command));
     }
+  });
+}
+
+/// The next twist of a shortest solution in move notation, or an empty string when solved.
+/// With `highlight`, the pieces it would move stay lit and the rest are dimmed
+/// until the next twist is made.
+#[wasm_bindgen]
+pub fn hint(highlight: bool) -> Result<String> {
+  stop_timed_solve();
+  let puzzle = STATE.with_borrow(|state| state.target());
+  let twist = SOLVER.with(|solver| solver.hint(&puzzle));
+  STATE.with_borrow_mut(|state| state.highlight = twist.filter(|_| highlight));
+  refresh_colors()?;
+  Ok(twist.map(notation::name).unwrap_or_default())
+}
 
-    set_vertex_positions(&gl, &p.get_vertex_positions());
+/// Stop highlighting the pieces of a hint.
+#[wasm_bindgen]
+pub fn clear_hint() -> Result<()> {
+  STATE.with_borrow_mut(|state| state.highlight = None);
+  refresh_colors()
+}
 
-    //set_vertex_colors(&gl, &p.get_vertex_colors());
-    //set_vertex_indices(&gl, &p.get_vertex_indices());
+/// Take back the last twist, as the Backspace key does.
+/// The history is simplified, so a played scramble and its solution undo to nothing.
+#[wasm_bindgen]
+pub fn undo() -> Result<()> {
+  let hinted = STATE.with_borrow_mut(|state| {
+    state.moves.press();
+    state.undo();
+    state.highlight.take().is_some()
+  });
+  if hinted {
+    refresh_colors()?;
+  }
+  Ok(())
+}
 
-    clear_scene(&gl);
-    resize_to_screen(&gl);
-    let n = p.get_index_count() as i32;
-    gl.draw_elements_with_i32(
-      WebGlRenderingContext::TRIANGLES,
-      n,
-      WebGlRenderingContext::UNSIGNED_INT,
-      0,
-    );
+/// Remember the twists made from now on, as the `r` key does.
+#[wasm_bindgen]
+pub fn start_recording() {
+  STATE.with_borrow_mut(|state| state.start_recording());
+}
+
+/// Keep the twists made since `start_recording` as a macro with this name,
+/// replacing any macro of the same name. Returns them in move notation.
+#[wasm_bindgen]
+pub fn stop_recording(name: &str) -> Result<String> {
+  macros::check_name(name)?;
+  let twists = STATE.with_borrow_mut(|state| {
+    let index = state.stop_recording(Some(name))?;
+    Some(notation::format(&state.macros[index].twists))
   });
+  save_macros()?;
+  Ok(twists.unwrap_or_default())
+}
+
+/// Names of the recorded macros. The first nine play on the digit keys,
+/// and backwards with shift.
+#[wasm_bindgen]
+pub fn macros() -> Vec<String> {
+  STATE.with_borrow(|state| state.macros.iter().map(|m| m.name.clone()).collect())
+}
+
+/// Queue the twists of a macro, or with `inverted` the twists that undo it.
+#[wasm_bindgen]
+pub fn play_macro(name: &str, inverted: bool) -> Result<()> {
+  let hinted = STATE.with_borrow_mut(|state| {
+    let index = state
+      .macro_index(name)
+      .ok_or_else(|| format!("no macro named {name}"))?;
+    state.play_macro(index, inverted);
+    Ok::<_, String>(state.highlight.take().is_some())
+  })?;
+  if hinted {
+    refresh_colors()?;
+  }
   Ok(())
 }
 
+/// Play a macro whenever `key` goes down, replacing what the key did before.
+/// Keys are named as `KeyboardEvent.key` names them.
 #[wasm_bindgen]
-pub fn on_key_down(event: &KeyboardEvent) {
-  console::log_2(&JsValue::from("keydown"), &JsValue::from(event));
-  KEYMAP.with(|keymap| {
-    STATE.with_borrow_mut(|state| {
-      let key: String = event.key();
-      let b: &str = &key;
-      if let Some(command) = keymap.get(b) {
-        match command {
-          Command::Camera { axis, orientation } => {
-            let val = match orientation {
-              Orientation::Positive => 1.0,
-              Orientation::Negative => -1.0,
-            };
-            match axis {
-              Axis::X => {
-                state.camera_axis[0] = val;
-              }
-              Axis::Y => {
-                state.camera_axis[1] = val;
-              }
-              Axis::Z => {
-                state.camera_axis[2] = val;
-              }
-            }
-          }
-          Command::Twist { octant } => {
-            state
-              .twist_buffer
-              .push_back(Twist::Center { octant: *octant });
-          }
-        }
+pub fn bind_macro(name: &str, key: &str, inverted: bool) -> Result<()> {
+  let index = STATE
+    .with_borrow(|state| state.macro_index(name))
+    .ok_or_else(|| format!("no macro named {name}"))?;
+  KEYMAP
+    .with_borrow_mut(|keymap| keymap.insert(key.to_string(), Command::Macro { index, inverted }));
+  save_macros()
+}
+
+// the puzzle, camera and settings outlive the page in local storage,
+// checked for changes at most this often
+const STATE_KEY: &str = "state";
+const SAVE_INTERVAL: f32 = 1000.;
+
+fn save_state(state: &State) -> Result<()> {
+  let mut fields = Fields::new();
+  state.save(&mut fields);
+  let settings = SETTINGS.get();
+  fields.set("antialiasing", format!("{:?}", settings.antialiasing));
+  fields.set("resolution_scale", settings.resolution_scale);
+  fields.set("patterns", settings.patterns);
+  fields.set("ground_shadow", settings.ground_shadow);
+  fields.set(
+    "background",
+    match BACKGROUND.get() {
+      Background::Solid(color) => format!("solid {}", color.to_hex()),
+      Background::Gradient { top, bottom } => {
+        format!("gradient {} {}", top.to_hex(), bottom.to_hex())
       }
-    })
-  })
+      Background::Environment => "environment".to_string(),
+    },
+  );
+  let text = fields.to_string();
+  if SAVED.with_borrow(|saved| *saved != text) {
+    local_storage()?.set_item(STATE_KEY, &text)?;
+    SAVED.set(text);
+  }
+  Ok(())
+}
+
+/// Put back the last session's puzzle, camera and settings.
+/// Saves this build can't read are left alone, and overwritten once anything changes.
+fn load_state() -> Result<()> {
+  let Some(text) = local_storage()?.get_item(STATE_KEY)? else {
+    return Ok(());
+  };
+  let fields = Fields::parse(&text)?;
+  SOLVER.with(|solver| STATE.with_borrow_mut(|state| state.restore(&fields, solver)))?;
+  if fields.get("colors").is_some() {
+    // a scheme added at runtime comes back with the save, so it can be picked by name again
+    let scheme = STATE.with_borrow(|state| state.color_scheme.clone());
+    COLOR_SCHEMES.with_borrow_mut(|schemes| {
+      if !schemes.iter().any(|s| s.name == scheme.name) {
+        schemes.push(scheme);
+      }
+    });
+  } else if let Some(name) = fields.get("scheme") {
+    let scheme =
+      COLOR_SCHEMES.with_borrow(|schemes| schemes.iter().find(|s| s.name == name).cloned());
+    if let Some(scheme) = scheme {
+      STATE.with_borrow_mut(|state| state.color_scheme = scheme);
+    }
+  }
+
+  let mut settings = RenderSettings::default();
+  let flag = |name| fields.get(name).map(|v| v == "true");
+  settings.antialiasing = match fields.get("antialiasing") {
+    Some("Off") => Antialiasing::Off,
+    Some("Fxaa") => Antialiasing::Fxaa,
+    _ => Antialiasing::Msaa,
+  };
+  if let Some(scale) = fields.get("resolution_scale").and_then(|v| v.parse().ok()) {
+    settings.resolution_scale = scale;
+  }
+  settings.patterns = flag("patterns").unwrap_or(settings.patterns);
+  settings.ground_shadow = flag("ground_shadow").unwrap_or(settings.ground_shadow);
+  if settings.resolution_scale.is_finite() && settings.resolution_scale > 0.0 {
+    SETTINGS.set(settings);
+  }
+
+  let words: Vec<_> = fields.get("background").unwrap_or("").split(' ').collect();
+  let background = match words[..] {
+    ["solid", color] => Some(Background::Solid(Color::from_hex(color)?)),
+    ["gradient", top, bottom] => Some(Background::Gradient {
+      top: Color::from_hex(top)?,
+      bottom: Color::from_hex(bottom)?,
+    }),
+    ["environment"] => Some(Background::Environment),
+    _ => None,
+  };
+  if let Some(background) = background {
+    BACKGROUND.set(background);
+  }
+  SAVED.set(text);
+  Ok(())
 }
 
+/// A link to this page showing the puzzle as it will be once queued twists are done,
+/// with the moves made since the last reset when `with_history`.
 #[wasm_bindgen]
-pub fn on_key_up(event: &KeyboardEvent) {
-  KEYMAP.with(|keymap| {
+pub fn share_link(with_history: bool) -> Result<String> {
+  let location = window().ok_or("no window")?.location();
+  let fragment = STATE.with_borrow(|state| {
+    share::encode(&state.target(), with_history.then_some(&state.history[..]))
+  })?;
+  Ok(format!(
+    "{}{}{}#{fragment}",
+    location.origin()?,
+    location.pathname()?,
+    location.search()?
+  ))
+}
+
+/// Why the link the page was opened with couldn't be shown, if it couldn't.
+#[wasm_bindgen]
+pub fn link_error() -> Option<String> {
+  LINK_ERROR.with_borrow(Clone::clone)
+}
+
+// show the position in the page's fragment, then drop the fragment
+// so reloading goes back to the saved session
+fn load_link() -> Result<()> {
+  let window = window().ok_or("no window")?;
+  let location = window.location();
+  let hash = location.hash()?;
+  if hash.len() <= 1 {
+    return Ok(());
+  }
+  let shared = share::decode(&hash)?;
+  SOLVER.with(|solver| {
     STATE.with_borrow_mut(|state| {
-      let key: String = event.key();
-      let b: &str = &key;
-      if let Some(Command::Camera { axis, .. }) = keymap.get(b) {
-        match axis {
-          Axis::X => {
-            state.camera_axis[0] = 0.0;
-          }
-          Axis::Y => {
-            state.camera_axis[1] = 0.0;
-          }
-          Axis::Z => {
-            state.camera_axis[2] = 0.0;
-          }
-        }
-      }
+      state.arrange(&shared.puzzle, solver)?;
+      state.history = shared.history;
+      Ok::<_, String>(())
     })
+  })?;
+  let url = format!("{}{}", location.pathname()?, location.search()?);
+  window
+    .history()?
+    .replace_state_with_url(&JsValue::NULL, "", Some(&url))
+}
+
+// macros outlive the page in local storage
+const MACROS_KEY: &str = "macros";
+
+fn save_macros() -> Result<()> {
+  let text =
+    STATE.with_borrow(|state| KEYMAP.with_borrow(|keymap| macros::save(&state.macros, keymap)));
+  local_storage()?.set_item(MACROS_KEY, &text)
+}
+
+fn load_macros() -> Result<()> {
+  let Some(text) = local_storage()?.get_item(MACROS_KEY)? else {
+    return Ok(());
+  };
+  let (macros, bindings) = macros::load(&text)?;
+  STATE.with_borrow_mut(|state| state.macros = macros);
+  KEYMAP.with_borrow_mut(|keymap| keymap.extend(bindings));
+  Ok(())
+}
+
+fn local_storage() -> Result<Storage> {
+  window()
+    .ok_or("no window")?
+    .local_storage()?
+    .ok_or_else(|| "no local storage".into())
+}
+
+/// Where the solve timer is, see `timer::Phase`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerPhase {
+  Idle,
+  Inspecting,
+  Ready,
+  Solving,
+  Solved,
+}
+
+/// Session statistics in milliseconds, undefined until there are enough solves.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct SolveStats {
+  pub solves: usize,
+  pub best: Option<f32>,
+  pub mean: Option<f32>,
+  pub ao5: Option<f32>,
+  pub ao12: Option<f32>,
+  pub fewest_twists: Option<usize>,
+}
+
+/// Moves made since the last timed solve began, see `metric::MoveCount`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct Moves {
+  pub twists: usize,
+  pub turns: usize,
+  pub presses: usize,
+}
+
+#[wasm_bindgen]
+pub fn move_count() -> Moves {
+  let moves = STATE.with_borrow(|state| state.moves);
+  Moves {
+    twists: moves.twists,
+    turns: moves.turns,
+    presses: moves.presses,
+  }
+}
+
+/// Every solve of the session as CSV: time in seconds, twists, turns and key presses.
+#[wasm_bindgen]
+pub fn export_session() -> String {
+  TIMER.with_borrow(|timer| timer.export())
+}
+
+/// Reset the puzzle to a random state and start the solve timer.
+/// With `inspection` there are 15 seconds to look before the clock starts,
+/// otherwise it starts with the first twist. Returns the scramble in move notation.
+#[wasm_bindgen]
+pub fn start_timed_solve(seed: u32, inspection: bool) -> Result<String> {
+  let twists = reset_scrambled(seed)?;
+  TIMER.with_borrow_mut(|timer| timer.arm(inspection));
+  Ok(twists)
+}
+
+/// Reset the puzzle to a random state at once, as the Scramble button does,
+/// without counting the scramble's twists or letting them be undone.
+/// A timed solve under way is dropped. Returns the scramble in move notation.
+#[wasm_bindgen]
+pub fn scramble_puzzle(seed: u32) -> Result<String> {
+  stop_timed_solve();
+  reset_scrambled(seed)
+}
+
+fn reset_scrambled(seed: u32) -> Result<String> {
+  let twists = SOLVER.with(|solver| scramble::scramble(solver, seed.into()).twists);
+  STATE.with_borrow_mut(|state| {
+    state.reset();
+    state.twist_buffer.extend(&twists);
+    state.finish_twists();
+  });
+  refresh_colors()?;
+  Ok(notation::format(&twists))
+}
+
+/// Stop the timer without counting the solve.
+#[wasm_bindgen]
+pub fn cancel_timed_solve() {
+  TIMER.with_borrow_mut(|timer| timer.cancel());
+}
+
+// help from the solver or played moves aren't the solver's own, so a solve under way
+// is dropped rather than recorded
+fn stop_timed_solve() {
+  TIMER.with_borrow_mut(|timer| {
+    if matches!(
+      timer.phase,
+      Phase::Inspecting { .. } | Phase::Ready | Phase::Solving { .. }
+    ) {
+      timer.cancel();
+    }
   });
 }
 
+#[wasm_bindgen]
+pub fn timer_phase() -> TimerPhase {
+  TIMER.with_borrow(|timer| match timer.phase {
+    Phase::Idle => TimerPhase::Idle,
+    Phase::Inspecting { .. } => TimerPhase::Inspecting,
+    Phase::Ready => TimerPhase::Ready,
+    Phase::Solving { .. } => TimerPhase::Solving,
+    Phase::Solved { .. } => TimerPhase::Solved,
+  })
+}
+
+/// Milliseconds to show: inspection left, the time so far, or the final time,
+/// as of the last rendered frame.
+#[wasm_bindgen]
+pub fn timer_display() -> f32 {
+  TIMER.with_borrow(|timer| timer.display())
+}
+
+#[wasm_bindgen]
+pub fn solve_stats() -> SolveStats {
+  TIMER.with_borrow(|timer| {
+    let stats = timer.stats();
+    SolveStats {
+      solves: timer.solves.len(),
+      best: stats.best,
+      mean: stats.mean,
+      ao5: stats.ao5,
+      ao12: stats.ao12,
+      fewest_twists: stats.fewest_twists,
+    }
+  })
+}
+
+/// Twists from solved to a uniformly random state, in move notation.
+/// The same seed always gives the same scramble.
+#[wasm_bindgen]
+pub fn scramble(seed: u32) -> String {
+  SOLVER.with(|solver| notation::format(&scramble::scramble(solver, seed.into()).twists))
+}
+
+/// A move sequence with its commutators, conjugates and repetitions written out,
+/// and with `cancel` simplified as `notation::simplify` does.
+#[wasm_bindgen]
+pub fn expand(moves: &str, cancel: bool) -> Result<String> {
+  let mut twists = notation::parse(moves)?;
+  if cancel {
+    twists = notation::simplify(&twists);
+  }
+  Ok(notation::format(&twists))
+}
+
+/// What a move sequence does on its own from solved: its cycles of pieces,
+/// the squares it turns, its order and whether it is written as a commutator.
+#[wasm_bindgen]
+pub fn analyze(moves: &str) -> Result<String> {
+  Ok(effect::analyze(&notation::parse(moves)?).to_string())
+}
+
+/// The puzzle once the queued twists are done, for the solver worker's `Job`.
+#[wasm_bindgen]
+pub fn puzzle_state() -> Vec<u8> {
+  STATE.with_borrow(|state| state.target().to_bytes())
+}
+
+/// Queue twists written in move notation, such as the result of `solve`.
+#[wasm_bindgen]
+pub fn play(moves: &str) -> Result<()> {
+  let twists = notation::parse(moves)?;
+  stop_timed_solve();
+  let hinted = STATE.with_borrow_mut(|state| {
+    for twist in twists {
+      state.queue(twist);
+    }
+    state.highlight.take().is_some()
+  });
+  if hinted {
+    refresh_colors()?;
+  }
+  Ok(())
+}
+
+/// Names of the color schemes available to `set_color_scheme`.
+#[wasm_bindgen]
+pub fn color_schemes() -> Vec<String> {
+  COLOR_SCHEMES.with_borrow(|schemes| schemes.iter().map(|s| s.name.clone()).collect())
+}
+
+#[wasm_bindgen]
+pub fn set_color_scheme(name: &str) -> Result<()> {
+  let scheme = COLOR_SCHEMES
+    .with_borrow(|schemes| schemes.iter().find(|s| s.name == name).cloned())
+    .ok_or_else(|| format!("unknown color scheme: {name}"))?;
+  STATE.with_borrow_mut(|state| state.color_scheme = scheme);
+  refresh_colors()
+}
+
+/// Register a user defined color scheme, replacing any scheme with the same name.
+/// Colors are css hex strings like `#ff8800`.
+/// Squares are listed white, yellow, blue, green, red, orange
+/// and hexagons white, pink, red, blue, yellow, silver, orange, green,
+/// matching the factory colors of each face.
+#[wasm_bindgen]
+pub fn add_color_scheme(name: String, squares: Vec<String>, hexes: Vec<String>) -> Result<()> {
+  let scheme = ColorScheme::from_hex(name, &squares, &hexes)?;
+  COLOR_SCHEMES.with_borrow_mut(|schemes| {
+    schemes.retain(|s| s.name != scheme.name);
+    schemes.push(scheme);
+  });
+  Ok(())
+}
+
+/// Upload the vertex colors of the current scheme.
+/// Only the color buffer changes; piece geometry is untouched.
+fn refresh_colors() -> Result<()> {
+  with_backend(|backend| {
+    STATE.with_borrow(|p| backend.set_vertex_colors(&p.get_vertex_colors()));
+    Ok(())
+  })
+}
+
 const VERTEX_SHADER: &str = r##"
   attribute vec4 vertexPosition;
   attribute vec4 vertexColor;
+  attribute vec2 vertexUv;
+  attribute float vertexPattern;
 
   uniform mat4 transformMatrix;
 
   varying lowp vec4 fragmentColor;
+  varying mediump vec2 fragmentUv;
+  varying mediump float fragmentPattern;
 
   void main(void) {
     gl_Position = transformMatrix * vertexPosition;
     fragmentColor = vertexColor;
+    fragmentUv = vertexUv;
+    fragmentPattern = vertexPattern;
   }
 "##;
 
 const FRAGMENT_SHADER: &str = r##"
+  precision mediump float;
+
+  uniform sampler2D patterns;
+  uniform float patternStrength;
+
   varying lowp vec4 fragmentColor;
+  varying vec2 fragmentUv;
+  varying float fragmentPattern;
+
+  void main(void) {
+    // look up the facet's tile in the atlas, repeating it across the facet
+    float tile = floor(fragmentPattern + 0.5);
+    vec2 origin = vec2(mod(tile, ATLAS_COLUMNS), floor(tile / ATLAS_COLUMNS));
+    vec2 uv = (origin + fract(fragmentUv)) / vec2(ATLAS_COLUMNS, ATLAS_ROWS);
+    float mask = texture2D(patterns, uv).a * patternStrength;
+
+    // ink that contrasts with the facet color
+    float luma = dot(fragmentColor.rgb, vec3(0.299, 0.587, 0.114));
+    vec3 ink = luma > 0.5 ? vec3(0.0) : vec3(1.0);
+    gl_FragColor = vec4(mix(fragmentColor.rgb, ink, 0.6 * mask), fragmentColor.a);
+  }
+"##;
+
+// Draws a single triangle that covers the whole screen.
+const QUAD_VERTEX_SHADER: &str = r##"
+  attribute vec2 quadPosition;
+
+  varying vec2 texCoord;
+
+  void main(void) {
+    texCoord = quadPosition * 0.5 + 0.5;
+    gl_Position = vec4(quadPosition, 0.0, 1.0);
+  }
+"##;
+
+// Fast approximate anti-aliasing.
+// Blurs along edges detected from the luminance of neighboring pixels.
+const FXAA_SHADER: &str = r##"
+  precision mediump float;
+
+  uniform sampler2D frame;
+  uniform vec2 resolution;
+
+  varying vec2 texCoord;
+
+  const float REDUCE_MIN = 1.0 / 128.0;
+  const float REDUCE_MUL = 1.0 / 8.0;
+  const float SPAN_MAX = 8.0;
+
+  void main(void) {
+    vec2 texel = 1.0 / resolution;
+    vec3 rgbNW = texture2D(frame, texCoord + vec2(-1.0, -1.0) * texel).rgb;
+    vec3 rgbNE = texture2D(frame, texCoord + vec2(1.0, -1.0) * texel).rgb;
+    vec3 rgbSW = texture2D(frame, texCoord + vec2(-1.0, 1.0) * texel).rgb;
+    vec3 rgbSE = texture2D(frame, texCoord + vec2(1.0, 1.0) * texel).rgb;
+    vec4 rgbaM = texture2D(frame, texCoord);
+
+    vec3 luma = vec3(0.299, 0.587, 0.114);
+    float lumaNW = dot(rgbNW, luma);
+    float lumaNE = dot(rgbNE, luma);
+    float lumaSW = dot(rgbSW, luma);
+    float lumaSE = dot(rgbSE, luma);
+    float lumaM = dot(rgbaM.rgb, luma);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 dir = vec2(
+      (lumaSW + lumaSE) - (lumaNW + lumaNE),
+      (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, -SPAN_MAX, SPAN_MAX) * texel;
+
+    vec3 rgbA = 0.5 * (
+      texture2D(frame, texCoord + dir * (1.0 / 3.0 - 0.5)).rgb +
+      texture2D(frame, texCoord + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (
+      texture2D(frame, texCoord - dir * 0.5).rgb +
+      texture2D(frame, texCoord + dir * 0.5).rgb
+    );
+    float lumaB = dot(rgbB, luma);
+    if (lumaB < lumaMin || lumaB > lumaMax) {
+      gl_FragColor = vec4(rgbA, rgbaM.a);
+    } else {
+      gl_FragColor = vec4(rgbB, rgbaM.a);
+    }
+  }
+"##;
+
+// Paints the backdrop, either a vertical gradient or a procedural sky.
+// Rays are reconstructed from screen coordinates, so the sky turns with the camera
+// while the ground shadow stays put beneath the puzzle.
+const BACKGROUND_SHADER: &str = r##"
+  #ifdef GL_FRAGMENT_PRECISION_HIGH
+  precision highp float;
+  #else
+  precision mediump float;
+  #endif
+
+  uniform float mode;
+  uniform float shadow;
+  uniform vec3 topColor;
+  uniform vec3 bottomColor;
+  uniform mat4 inverseProjection;
+  uniform mat4 inverseTransform;
+
+  varying vec2 texCoord;
+
+  const float FLOOR = -2.2;
+
+  vec3 unproject(mat4 inverse, vec2 ndc, float depth) {
+    vec4 point = inverse * vec4(ndc, depth, 1.0);
+    return point.xyz / point.w;
+  }
+
+  vec3 ray(mat4 inverse, vec2 ndc) {
+    return normalize(unproject(inverse, ndc, 1.0) - unproject(inverse, ndc, -1.0));
+  }
+
+  vec3 environment(vec3 dir) {
+    vec3 zenith = vec3(0.18, 0.32, 0.58);
+    vec3 horizon = vec3(0.72, 0.78, 0.85);
+    vec3 ground = vec3(0.22, 0.2, 0.18);
+    if (dir.y > 0.0) {
+      vec3 sky = mix(horizon, zenith, sqrt(dir.y));
+      vec3 sun = normalize(vec3(0.4, 0.7, 0.6));
+      return sky + vec3(1.0, 0.9, 0.7) * pow(max(dot(dir, sun), 0.0), 64.0);
+    }
+    return mix(horizon * 0.6, ground, sqrt(-dir.y));
+  }
 
   void main(void) {
-    gl_FragColor = fragmentColor;
+    vec2 ndc = texCoord * 2.0 - 1.0;
+    vec3 color = mode < 0.5
+      ? mix(bottomColor, topColor, texCoord.y)
+      : environment(ray(inverseTransform, ndc));
+
+    if (shadow > 0.5) {
+      // blob shadow on a floor below the puzzle's center
+      vec3 origin = unproject(inverseProjection, ndc, -1.0);
+      vec3 dir = ray(inverseProjection, ndc);
+      if (dir.y < 0.0) {
+        vec3 hit = origin + dir * (FLOOR - origin.y) / dir.y;
+        color *= 1.0 - 0.6 * (1.0 - smoothstep(0.4, 2.2, length(hit.xz)));
+      }
+    }
+    gl_FragColor = vec4(color, 1.0);
   }
 "##;
 
@@ -232,26 +853,241 @@ fn start() -> Result<()> {
     use console_error_panic_hook;
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
   }
+  // a page without storage, or with saves it can't read, still works without them
+  if let Err(error) = load_macros() {
+    console::warn_2(&JsValue::from("could not load macros"), &error);
+  }
+  // before the canvas, whose context depends on the antialiasing setting
+  if let Err(error) = load_state() {
+    console::warn_2(&JsValue::from("could not restore the last session"), &error);
+  }
+  if let Err(error) = load_link() {
+    console::error_2(&JsValue::from("could not show the linked puzzle"), &error);
+    LINK_ERROR.set(Some(
+      error.as_string().unwrap_or_else(|| format!("{error:?}")),
+    ));
+  }
+  init_gl()
+}
+
+/// Gpu resources, recreated whenever the canvas is.
+struct Pipeline {
+  scene: WebGlProgram,
+  fxaa: WebGlProgram,
+  background: WebGlProgram,
+  positions: WebGlBuffer,
+  colors: WebGlBuffer,
+  uvs: WebGlBuffer,
+  patterns: WebGlBuffer,
+  indices: WebGlBuffer,
+  quad: WebGlBuffer,
+  atlas: WebGlTexture,
+  // offscreen frame for post processing, allocated on first use
+  target: Option<RenderTarget>,
+  camera: Mat4,
+  index_count: i32,
+}
 
+struct RenderTarget {
+  framebuffer: WebGlFramebuffer,
+  texture: WebGlTexture,
+  depth: WebGlRenderbuffer,
+  width: i32,
+  height: i32,
+}
+
+fn init_gl() -> Result<()> {
   let gl = webgl_context()?;
   // enable u32 type
   gl.get_extension("OES_element_index_uint")?;
 
   let vertex_shader = compile_shader(&gl, WebGlRenderingContext::VERTEX_SHADER, VERTEX_SHADER)?;
-  let fragment_shader =
-    compile_shader(&gl, WebGlRenderingContext::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
-  let program = link_program(&gl, &vertex_shader, &fragment_shader)?;
-  gl.use_program(Some(&program));
+  let fragment_shader = compile_shader(
+    &gl,
+    WebGlRenderingContext::FRAGMENT_SHADER,
+    &format!(
+      "#define ATLAS_COLUMNS {:.1}\n#define ATLAS_ROWS {:.1}\n{}",
+      pattern::ATLAS_COLUMNS as f32,
+      pattern::ATLAS_ROWS as f32,
+      FRAGMENT_SHADER
+    ),
+  )?;
+  let scene = link_program(&gl, &vertex_shader, &fragment_shader)?;
+
+  let quad_shader = compile_shader(
+    &gl,
+    WebGlRenderingContext::VERTEX_SHADER,
+    QUAD_VERTEX_SHADER,
+  )?;
+  let fxaa_shader = compile_shader(&gl, WebGlRenderingContext::FRAGMENT_SHADER, FXAA_SHADER)?;
+  let fxaa = link_program(&gl, &quad_shader, &fxaa_shader)?;
+  let background_shader = compile_shader(
+    &gl,
+    WebGlRenderingContext::FRAGMENT_SHADER,
+    BACKGROUND_SHADER,
+  )?;
+  let background = link_program(&gl, &quad_shader, &background_shader)?;
+
+  let pipeline = Pipeline {
+    scene,
+    fxaa,
+    background,
+    positions: create_buffer(&gl)?,
+    colors: create_buffer(&gl)?,
+    uvs: create_buffer(&gl)?,
+    patterns: create_buffer(&gl)?,
+    indices: create_buffer(&gl)?,
+    quad: create_buffer(&gl)?,
+    atlas: create_atlas(&gl)?,
+    target: None,
+    camera: mat4::create(),
+    index_count: 0,
+  };
+
+  gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&pipeline.quad));
+  gl.buffer_data_with_array_buffer_view(
+    WebGlRenderingContext::ARRAY_BUFFER,
+    &Float32Array::from([-1.0, -1.0, 3.0, -1.0, -1.0, 3.0].as_slice()),
+    WebGlRenderingContext::STATIC_DRAW,
+  );
 
-  PROJECTION.with(|projection| {
-    set_transform_matrix(&gl, &projection);
-  });
-  STATE.with_borrow(|p| {
-    set_vertex_colors(&gl, &p.get_vertex_colors());
-    set_vertex_indices(&gl, &p.get_vertex_indices());
-    set_vertex_positions(&gl, &p.get_vertex_positions());
-  });
-  Ok(())
+  gl.use_program(Some(&pipeline.scene));
+  gl.uniform1i(
+    gl.get_uniform_location(&pipeline.scene, "patterns")
+      .as_ref(),
+    ATLAS_TEXTURE_UNIT as i32,
+  );
+  PIPELINE.set(Some(pipeline));
+  with_backend(|backend| {
+    STATE.with_borrow(|p| render::upload(backend, p));
+    Ok(())
+  })
+}
+
+/// Draws to the page's canvas.
+struct WebGl<'a> {
+  gl: &'a WebGlRenderingContext,
+  pipeline: &'a mut Pipeline,
+  settings: RenderSettings,
+}
+
+fn with_backend<T>(f: impl FnOnce(&mut WebGl) -> Result<T>) -> Result<T> {
+  let gl = webgl_context()?;
+  PIPELINE.with_borrow_mut(|pipeline| {
+    let pipeline = pipeline.as_mut().ok_or("webgl is not initialized")?;
+    f(&mut WebGl {
+      gl: &gl,
+      pipeline,
+      settings: SETTINGS.get(),
+    })
+  })
+}
+
+impl Backend for WebGl<'_> {
+  type Error = JsValue;
+
+  fn set_camera(&mut self, camera: &Mat4) {
+    self.pipeline.camera = *camera;
+    self.gl.use_program(Some(&self.pipeline.scene));
+    set_camera(self.gl, camera);
+  }
+
+  fn set_vertex_positions(&mut self, positions: &[f32]) {
+    set_vertex_positions(self.gl, self.pipeline, &Float32Array::from(positions));
+  }
+
+  fn set_vertex_colors(&mut self, colors: &[f32]) {
+    set_vertex_colors(self.gl, self.pipeline, &Float32Array::from(colors));
+  }
+
+  fn set_vertex_patterns(&mut self, uvs: &[f32], patterns: &[f32]) {
+    set_vertex_patterns(
+      self.gl,
+      self.pipeline,
+      &Float32Array::from(uvs),
+      &Float32Array::from(patterns),
+    );
+  }
+
+  fn set_vertex_indices(&mut self, indices: &[u32]) {
+    self.pipeline.index_count = indices.len() as i32;
+    set_vertex_indices(self.gl, self.pipeline, &Uint32Array::from(indices));
+  }
+
+  fn draw(&mut self, scene: &Scene) -> Result<()> {
+    let gl = self.gl;
+    let pipeline = &mut *self.pipeline;
+    let fxaa = self.settings.antialiasing == Antialiasing::Fxaa;
+    resize_to_screen(gl, self.settings.resolution_scale);
+
+    if fxaa {
+      bind_render_target(gl, pipeline)?;
+    }
+
+    clear_scene(gl);
+    draw_background(gl, pipeline, scene);
+
+    gl.use_program(Some(&pipeline.scene));
+    gl.uniform1f(
+      gl.get_uniform_location(&pipeline.scene, "patternStrength")
+        .as_ref(),
+      if scene.patterns { 1.0 } else { 0.0 },
+    );
+    gl.active_texture(WebGlRenderingContext::TEXTURE0 + ATLAS_TEXTURE_UNIT);
+    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&pipeline.atlas));
+    gl.active_texture(WebGlRenderingContext::TEXTURE0);
+    bind_scene_attributes(gl, pipeline);
+    gl.draw_elements_with_i32(
+      WebGlRenderingContext::TRIANGLES,
+      pipeline.index_count,
+      WebGlRenderingContext::UNSIGNED_INT,
+      0,
+    );
+
+    if fxaa {
+      apply_fxaa(gl, pipeline);
+    }
+    Ok(())
+  }
+}
+
+fn create_buffer(gl: &WebGlRenderingContext) -> Result<WebGlBuffer> {
+  Ok(gl.create_buffer().ok_or("Unable to create buffer")?)
+}
+
+// The atlas keeps a texture unit to itself, leaving unit 0 for post processing.
+const ATLAS_TEXTURE_UNIT: u32 = 1;
+
+fn create_atlas(gl: &WebGlRenderingContext) -> Result<WebGlTexture> {
+  let texture = gl.create_texture().ok_or("Unable to create texture")?;
+  gl.active_texture(WebGlRenderingContext::TEXTURE0 + ATLAS_TEXTURE_UNIT);
+  gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+  gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+    WebGlRenderingContext::TEXTURE_2D,
+    0,
+    WebGlRenderingContext::RGBA as i32,
+    pattern::ATLAS_WIDTH as i32,
+    pattern::ATLAS_HEIGHT as i32,
+    0,
+    WebGlRenderingContext::RGBA,
+    WebGlRenderingContext::UNSIGNED_BYTE,
+    Some(&pattern::atlas()),
+  )?;
+  // nearest filtering keeps neighboring tiles from bleeding into each other
+  for (param, value) in [
+    (
+      WebGlRenderingContext::TEXTURE_MIN_FILTER,
+      WebGlRenderingContext::NEAREST,
+    ),
+    (
+      WebGlRenderingContext::TEXTURE_MAG_FILTER,
+      WebGlRenderingContext::NEAREST,
+    ),
+  ] {
+    gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, param, value as i32);
+  }
+  gl.active_texture(WebGlRenderingContext::TEXTURE0);
+  Ok(texture)
 }
 
 fn get_program(context: &WebGlRenderingContext) -> WebGlProgram {
@@ -316,470 +1152,99 @@ fn link_program(
   }
 }
 
-fn webgl_context() -> Result<WebGlRenderingContext> {
-  let gl = window()
+fn canvas() -> Result<HtmlCanvasElement> {
+  let canvas = window()
     .ok_or("no window")?
     .document()
     .ok_or("no document")?
     .query_selector("canvas")?
     .ok_or("no canvas")?
-    .dyn_into::<HtmlCanvasElement>()?
-    .get_context("webgl")?
+    .dyn_into::<HtmlCanvasElement>()?;
+  Ok(canvas)
+}
+
+fn webgl_context() -> Result<WebGlRenderingContext> {
+  let mut attributes = WebGlContextAttributes::new();
+  attributes.antialias(SETTINGS.get().antialiasing == Antialiasing::Msaa);
+  let gl = canvas()?
+    .get_context_with_context_options("webgl", &attributes)?
     .ok_or("no web gl context")?
     .dyn_into::<WebGlRenderingContext>()?;
   Ok(gl)
 }
 
-#[derive(Clone, Copy, Debug)]
-enum Twist {
-  Center { octant: u8 },
-}
-impl Twist {
-  fn positive(&self) -> bool {
-    let Twist::Center { octant } = self;
-    octant & 4 > 0
-  }
-
-  fn to_normal(&self) -> Vec3 {
-    match self {
-      //Twist::Side { .. } => todo!(),
-      Twist::Center { octant } => {
-        let mut axis = [-1., -1., 1.];
-        if octant & 1 != 0 {
-          axis[0] = 1.;
-        }
-
-        if octant & 2 != 0 {
-          axis[1] = 1.;
-        }
-
-        axis
-      }
-    }
-  }
-
-  fn to_matrix(&self, frame: f32) -> Mat4 {
-    let mut angle = ((2. * PI) / 3.) * f32::min(1., frame / ANIMATION_DURATION);
-    if !self.positive() {
-      angle *= -1.;
-    }
-
-    let mut matrix = mat4::create();
-    mat4::from_rotation(&mut matrix, angle, &self.to_normal());
-    matrix
-  }
-}
-
-#[derive(Debug)]
-struct Piece {
-  pub normal: Vec3,
-  pub facets: Vec<Facet>,
-}
-
-impl From<Facet> for Piece {
-  fn from(value: Facet) -> Self {
-    Self {
-      normal: value.normal,
-      facets: vec![value],
-    }
-  }
-}
-impl Piece {
-  fn transform(&mut self, matrix: &Mat4) {
-    let mut temp = [0.; 3];
-    vec3::transform_mat4(&mut temp, &self.normal, matrix);
-    self.normal = temp;
-    for facet in self.facets.iter_mut() {
-      facet.transform(matrix);
-    }
-  }
-}
-
-#[derive(Debug)]
-struct State {
-  camera_transform: Mat4,
-  camera_axis: Vec3,
-  frame: f32,
-  then: f32,
-  pieces: Vec<Piece>,
-  twist_buffer: VecDeque<Twist>,
-}
-
-impl State {
-  fn new() -> Self {
-    let mut camera_transform = mat4::create();
-    mat4::identity(&mut camera_transform);
-    let pieces = Self::init_pieces();
-    Self {
-      camera_transform,
-      camera_axis: vec3::create(),
-      frame: 0.0,
-      then: 0.0,
-      twist_buffer: VecDeque::new(),
-      pieces,
-    }
-  }
-
-  fn complete_twist(&mut self) {
-    if let Some(twist) = self.twist_buffer.pop_front() {
-      let normal = twist.to_normal();
-      let twist = twist.to_matrix(self.frame);
-      for piece in self.pieces.iter_mut() {
-        if vec3::dot(&normal, &piece.normal) > 0. {
-          piece.transform(&twist)
-        }
-      }
-    }
-    self.frame = 0.;
-  }
-
-  fn facets(&self) -> impl Iterator<Item = &Facet> {
-    self.pieces.iter().flat_map(|p| p.facets.iter())
-  }
-
-  fn init_pieces() -> Vec<Piece> {
-    // 8 hexagonal faces
-    // each hexagon is split into 3 trapezoid facets and 1 center triangle
-    // 6 square faces
-    // 4 cross sections
-    let mut pieces = Vec::with_capacity(6 + 8);
-
-    // depth of a square from the center of the puzzle
-    let a = 1.5;
-    // square sidelength
-    let b = 2.0_f32.sqrt() / 2.0;
-    let c = (a + b) / 2.0;
-    // border width
-    let w = 0.1;
-
-    let mut rot_x = mat4::create();
-    mat4::from_x_rotation(&mut rot_x, PI / 2.0);
-
-    let mut rot_y = mat4::create();
-    mat4::from_y_rotation(&mut rot_y, PI / 2.0);
-    let square1 = Facet {
-      normal: [0.0, 0.0, 1.0],
-      color: Color::CYAN,
-      mesh: vec![
-        b, 0., a, //
-        0., b, a, //
-        -b, 0., a, //
-        0., -b, a, //
-      ],
-    };
-
-    let square2 = square1.clone_with(&rot_x, Color::VIOLET);
-    let square3 = square2.clone_with(&rot_x, Color::LIGHT_RED);
-    let square4 = square3.clone_with(&rot_x, Color::BLUE_VIOLET);
-    let square5 = square1.clone_with(&rot_y, Color::TEAL);
-    let square6 = square3.clone_with(&rot_y, Color::SKY_BLUE);
-
-    let triangle1 = Facet {
-      color: Color::SILVER,
-      normal: [1., 1., 1.],
-      mesh: vec![
-        c, 0., c, //
-        0., c, c, //
-        c, c, 0., //
-      ],
-    };
-
-    let triangle2 = triangle1.clone_with(&rot_x, Color::WHITE);
-    let triangle3 = triangle2.clone_with(&rot_x, Color::ORANGE);
-    let triangle4 = triangle3.clone_with(&rot_x, Color::BLUE);
-    let triangle5 = triangle3.clone_with(&rot_y, Color::MAGENTA);
-    let triangle6 = triangle4.clone_with(&rot_y, Color::YELLOW);
-    let triangle7 = triangle5.clone_with(&rot_y, Color::GREEN);
-    let triangle8 = triangle6.clone_with(&rot_y, Color::CORAL);
-
-    let trapezoid1a = Facet {
-      color: Color::SILVER,
-      normal: [1., 1., 1.],
-      mesh: vec![
-        c, 0., c, //
-        0., c, c, //
-        0., b, a, //
-        b, 0., a, //
-      ],
-    };
-
-    let mut rot_oct1 = mat4::create();
-    mat4::from_rotation(&mut rot_oct1, 2.0 * PI / 3.0, &[1., 1., 1.]);
-    let trapezoid1b = trapezoid1a.clone_with(&rot_oct1, Color::SILVER);
-    let trapezoid1c = trapezoid1b.clone_with(&rot_oct1, Color::SILVER);
-
-    let trapezoid2a = trapezoid1a.clone_with(&rot_x, Color::WHITE);
-    let trapezoid2b = trapezoid1b.clone_with(&rot_x, Color::WHITE);
-    let trapezoid2c = trapezoid1c.clone_with(&rot_x, Color::WHITE);
-
-    let trapezoid3a = trapezoid2a.clone_with(&rot_x, Color::ORANGE);
-    let trapezoid3b = trapezoid2b.clone_with(&rot_x, Color::ORANGE);
-    let trapezoid3c = trapezoid2c.clone_with(&rot_x, Color::ORANGE);
-
-    let trapezoid4a = trapezoid3a.clone_with(&rot_x, Color::BLUE);
-    let trapezoid4b = trapezoid3b.clone_with(&rot_x, Color::BLUE);
-    let trapezoid4c = trapezoid3c.clone_with(&rot_x, Color::BLUE);
-
-    let trapezoid5a = trapezoid3a.clone_with(&rot_y, Color::MAGENTA);
-    let trapezoid5b = trapezoid3b.clone_with(&rot_y, Color::MAGENTA);
-    let trapezoid5c = trapezoid3c.clone_with(&rot_y, Color::MAGENTA);
-
-    let trapezoid6a = trapezoid4a.clone_with(&rot_y, Color::YELLOW);
-    let trapezoid6b = trapezoid4b.clone_with(&rot_y, Color::YELLOW);
-    let trapezoid6c = trapezoid4c.clone_with(&rot_y, Color::YELLOW);
-
-    let trapezoid7a = trapezoid5a.clone_with(&rot_y, Color::GREEN);
-    let trapezoid7b = trapezoid5b.clone_with(&rot_y, Color::GREEN);
-    let trapezoid7c = trapezoid5c.clone_with(&rot_y, Color::GREEN);
-
-    let trapezoid8a = trapezoid6a.clone_with(&rot_y, Color::CORAL);
-    let trapezoid8b = trapezoid6b.clone_with(&rot_y, Color::CORAL);
-    let trapezoid8c = trapezoid6c.clone_with(&rot_y, Color::CORAL);
-
-    pieces.push(Piece {
-      normal: square1.normal,
-      facets: vec![square1, trapezoid1a, trapezoid2c, trapezoid7a, trapezoid8c],
-    });
-    pieces.push(Piece {
-      normal: square2.normal,
-      facets: vec![square2, trapezoid2a, trapezoid7c, trapezoid3c, trapezoid5c],
-    });
-    pieces.push(Piece {
-      normal: square3.normal,
-      facets: vec![square3, trapezoid3a, trapezoid5b, trapezoid4c, trapezoid6b],
-    });
-    pieces.push(Piece {
-      normal: square4.normal,
-      facets: vec![square4, trapezoid4a, trapezoid6a, trapezoid1c, trapezoid8a],
-    });
-    pieces.push(Piece {
-      normal: square5.normal,
-      facets: vec![square5, trapezoid1b, trapezoid3b, trapezoid2b, trapezoid4b],
-    });
-    pieces.push(Piece {
-      normal: square6.normal,
-      facets: vec![square6, trapezoid5a, trapezoid6c, trapezoid7b, trapezoid8b],
-    });
-
-    pieces.push(triangle1.into());
-    pieces.push(triangle2.into());
-    pieces.push(triangle3.into());
-    pieces.push(triangle4.into());
-    pieces.push(triangle5.into());
-    pieces.push(triangle6.into());
-    pieces.push(triangle7.into());
-    pieces.push(triangle8.into());
-    pieces
-  }
-
-  fn get_vertex_count(&self) -> u32 {
-    self.facets().map(|f| f.get_vertex_count()).sum()
-  }
-
-  fn get_index_count(&self) -> u32 {
-    self.facets().map(|f| f.get_index_count()).sum()
-  }
-
-  fn get_vertex_indices(&self) -> Uint32Array {
-    let mut array = Vec::with_capacity(self.get_index_count() as usize);
-    let mut total: u32 = 0;
-    for facet in self.facets() {
-      let count = facet.get_vertex_count();
-      for i in 0..(count - 2) {
-        array.push(total);
-        array.push(total + i + 1);
-        array.push(total + i + 2);
-      }
-      total += count;
-    }
-    Uint32Array::from(array.as_slice())
-  }
-
-  fn get_vertex_colors(&self) -> Float32Array {
-    // n vertices times 4 rgba values
-    let mut array = Vec::with_capacity(4 * self.get_vertex_count() as usize);
-    for facet in self.facets() {
-      for _ in 0..facet.get_vertex_count() {
-        array.push(facet.color.red());
-        array.push(facet.color.green());
-        array.push(facet.color.blue());
-        array.push(facet.color.alpha());
-      }
-    }
-
-    Float32Array::from(array.as_slice())
-  }
-
-  fn get_vertex_positions(&self) -> Float32Array {
-    let mut vector = vec![0.0; self.get_vertex_count() as usize * 3];
-    let mut offset = 0;
-
-    // slow path
-    if let Some(twist) = self.twist_buffer.front() {
-      let normal = twist.to_normal();
-      for piece in self.pieces.iter() {
-        for facet in piece.facets.iter() {
-          let data: &mut [f32] = vector[offset..(offset + facet.mesh.len())].borrow_mut();
-          data.copy_from_slice(&facet.mesh);
-
-          if vec3::dot(&normal, &piece.normal) > 0. {
-            let mut mesh = Mesh { data };
-            mesh.transform(&twist.to_matrix(self.frame));
-          }
-          offset += facet.mesh.len();
-        }
-      }
-    }
-    // fast path
-    else {
-      for facet in self.facets() {
-        vector[offset..(offset + facet.mesh.len())].copy_from_slice(&facet.mesh);
-        offset += facet.mesh.len();
-      }
-    }
-
-    Float32Array::from(vector.as_slice())
-  }
-}
-
-#[derive(Debug, Clone)]
-struct Facet {
-  mesh: Vec<f32>,
-  normal: Vec3,
-  color: Color,
+/// Swap the canvas for a blank one, so the next context is created with the current settings.
+fn replace_canvas() -> Result<()> {
+  let canvas = canvas()?;
+  let fresh = canvas.clone_node()?;
+  canvas.replace_with_with_node_1(&fresh)?;
+  PIPELINE.set(None);
+  Ok(())
 }
 
-impl Facet {
-  fn clone_with(&self, matrix: &Mat4, color: Color) -> Facet {
-    let mut facet = self.clone();
-    facet.transform(matrix);
-    facet.color = color;
-    facet
-  }
-  fn transform(&mut self, matrix: &Mat4) {
-    let mut temp = [0.0_f32; 3];
-    vec3::transform_mat4(&mut temp, &self.normal, matrix);
-    self.normal = temp;
-
-    let n = self.mesh.len() / 3;
-
-    for i in 0..n {
-      let slice: &mut [f32] = self.mesh[3 * i..3 * i + 3].borrow_mut();
-      vec3::transform_mat4(&mut temp, &slice.try_into().unwrap(), matrix);
-      slice.copy_from_slice(temp.as_slice());
-    }
-  }
-
-  fn get_vertex_count(&self) -> u32 {
-    self.mesh.len() as u32 / 3
-  }
-
-  fn get_index_count(&self) -> u32 {
-    3 * (self.get_vertex_count() - 2)
-  }
+fn set_vertex_positions(gl: &WebGlRenderingContext, pipeline: &Pipeline, positions: &Float32Array) {
+  gl.bind_buffer(
+    WebGlRenderingContext::ARRAY_BUFFER,
+    Some(&pipeline.positions),
+  );
+  gl.buffer_data_with_array_buffer_view(
+    WebGlRenderingContext::ARRAY_BUFFER,
+    positions,
+    WebGlRenderingContext::DYNAMIC_DRAW,
+  );
+  bind_attribute(gl, &pipeline.scene, "vertexPosition", 3);
 }
 
-#[derive(Debug, Clone)]
-struct Color {
-  red: u8,
-  green: u8,
-  blue: u8,
-  alpha: f32,
+fn set_vertex_colors(gl: &WebGlRenderingContext, pipeline: &Pipeline, colors: &Float32Array) {
+  gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&pipeline.colors));
+  gl.buffer_data_with_array_buffer_view(
+    WebGlRenderingContext::ARRAY_BUFFER,
+    colors,
+    WebGlRenderingContext::DYNAMIC_DRAW,
+  );
+  bind_attribute(gl, &pipeline.scene, "vertexColor", 4);
 }
 
-impl Color {
-  const fn rgb(red: u8, green: u8, blue: u8) -> Self {
-    Color {
-      red,
-      green,
-      blue,
-      alpha: 1.,
-    }
-  }
-  const fn rgba(red: u8, green: u8, blue: u8, alpha: f32) -> Self {
-    Color {
-      red,
-      green,
-      blue,
-      alpha,
-    }
-  }
-
-  fn red(&self) -> f32 {
-    f32::from(self.red) / 255.
-  }
-
-  fn green(&self) -> f32 {
-    f32::from(self.green) / 255.
-  }
-
-  fn blue(&self) -> f32 {
-    f32::from(self.blue) / 255.
-  }
-
-  fn alpha(&self) -> f32 {
-    self.alpha
+fn set_vertex_patterns(
+  gl: &WebGlRenderingContext,
+  pipeline: &Pipeline,
+  uvs: &Float32Array,
+  patterns: &Float32Array,
+) {
+  for (buffer, data, name, num_components) in [
+    (&pipeline.uvs, uvs, "vertexUv", 2),
+    (&pipeline.patterns, patterns, "vertexPattern", 1),
+  ] {
+    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
+    gl.buffer_data_with_array_buffer_view(
+      WebGlRenderingContext::ARRAY_BUFFER,
+      data,
+      WebGlRenderingContext::STATIC_DRAW,
+    );
+    bind_attribute(gl, &pipeline.scene, name, num_components);
   }
-
-  const MAGENTA: Self = Self::rgb(210, 75, 208);
-  const SILVER: Self = Self::rgb(143, 143, 143);
-  const BLUE_VIOLET: Self = Self::rgb(119, 153, 252);
-  const SKY_BLUE: Self = Self::rgb(135, 206, 235);
-  const WHITE: Self = Self::rgb(212, 241, 252);
-  const LIGHT_GREEN: Self = Self::rgb(221, 250, 220);
-  const CORAL: Self = Self::rgb(244, 79, 130);
-  const RED: Self = Self::rgb(173, 25, 2);
-  const LIGHT_RED: Self = Self::rgb(232, 173, 191);
-  const LIGHT_PINK: Self = Self::rgb(252, 222, 255);
-  const LIGHT_PURPLE: Self = Self::rgb(196, 173, 234);
-  const CYAN: Self = Self::rgb(12, 249, 239);
-  const TEAL: Self = Self::rgb(33, 209, 163);
-  const VIOLET: Self = Self::rgb(94, 79, 160);
-  const YELLOW: Self = Self::rgb(178, 201, 43);
-  const BLUE: Self = Self::rgb(41, 67, 163);
-  const GREEN: Self = Self::rgb(35, 118, 49);
-  const ORANGE: Self = Self::rgb(235, 135, 21);
 }
 
-fn set_vertex_positions(gl: &WebGlRenderingContext, positions: &Float32Array) {
-  let program = get_program(gl);
-  let buffer = gl.create_buffer();
-  gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, buffer.as_ref());
-  gl.buffer_data_with_opt_array_buffer(
-    WebGlRenderingContext::ARRAY_BUFFER,
-    Some(&positions.buffer()),
-    WebGlRenderingContext::STATIC_DRAW,
-  );
-
-  let attribute_index = gl.get_attrib_location(&program, "vertexPosition") as u32;
-  let num_components = 3;
-  let array_type = WebGlRenderingContext::FLOAT;
-  let normalize = false;
-  let stride = 0;
-  let offset = 0;
-  gl.vertex_attrib_pointer_with_i32(
-    attribute_index,
-    num_components,
-    array_type,
-    normalize,
-    stride,
-    offset,
+fn set_vertex_indices(gl: &WebGlRenderingContext, pipeline: &Pipeline, indices: &Uint32Array) {
+  gl.bind_buffer(
+    WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+    Some(&pipeline.indices),
   );
-  gl.enable_vertex_attrib_array(attribute_index);
-}
-
-fn set_vertex_colors(gl: &WebGlRenderingContext, colors: &Float32Array) {
-  let program = get_program(gl);
-  let buffer = gl.create_buffer();
-  gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, buffer.as_ref());
-  gl.buffer_data_with_opt_array_buffer(
-    WebGlRenderingContext::ARRAY_BUFFER,
-    Some(&colors.buffer()),
+  gl.buffer_data_with_array_buffer_view(
+    WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
+    indices,
     WebGlRenderingContext::DYNAMIC_DRAW,
   );
+}
 
-  let attribute_index = gl.get_attrib_location(&program, "vertexColor") as u32;
-  let num_components = 4;
+/// Point an attribute of the program at the currently bound array buffer.
+fn bind_attribute(
+  gl: &WebGlRenderingContext,
+  program: &WebGlProgram,
+  name: &str,
+  num_components: i32,
+) {
+  let attribute_index = gl.get_attrib_location(program, name) as u32;
   let array_type = WebGlRenderingContext::FLOAT;
   let normalize = false;
   let stride = 0;
@@ -795,16 +1260,6 @@ fn set_vertex_colors(gl: &WebGlRenderingContext, colors: &Float32Array) {
   gl.enable_vertex_attrib_array(attribute_index);
 }
 
-fn set_vertex_indices(gl: &WebGlRenderingContext, indices: &Uint32Array) {
-  let buffer = gl.create_buffer();
-  gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, buffer.as_ref());
-  gl.buffer_data_with_opt_array_buffer(
-    WebGlRenderingContext::ELEMENT_ARRAY_BUFFER,
-    Some(&indices.buffer()),
-    WebGlRenderingContext::DYNAMIC_DRAW,
-  );
-}
-
 /* This is used for the camera
  * (maybe also for pieces rotation? )
  * */
@@ -818,17 +1273,18 @@ fn set_transform_matrix(gl: &WebGlRenderingContext, matrix: &Mat4) {
   )
 }
 
+fn set_camera(gl: &WebGlRenderingContext, camera: &Mat4) {
+  PROJECTION.with(|projection| {
+    let mut transform = mat4::create();
+    mat4::multiply(&mut transform, projection, camera);
+    set_transform_matrix(gl, &transform);
+  });
+}
+
 fn get_projection_matrix(gl: &WebGlRenderingContext, dest: &mut Mat4) {
   let canvas: HtmlElement = gl.canvas().unwrap().dyn_into::<HtmlElement>().unwrap();
-  let fov = (45.0 * std::f64::consts::PI as f32) / 180.0;
   let aspect = canvas.client_width() as f32 / canvas.client_height() as f32;
-  let near = 0.1;
-  let far = 100.0;
-  let mut p = mat4::create();
-  mat4::perspective(&mut p, fov, aspect, near, Some(far));
-  let mut t = mat4::create();
-  mat4::from_translation(&mut t, &[0.0, 0.0, -6.0]);
-  mat4::multiply(dest, &p, &t);
+  *dest = render::projection_matrix(aspect);
 }
 
 fn clear_scene(gl: &WebGlRenderingContext) {
@@ -839,59 +1295,212 @@ fn clear_scene(gl: &WebGlRenderingContext) {
   gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
 }
 
-fn resize_to_screen(gl: &WebGlRenderingContext) {
+fn resize_to_screen(gl: &WebGlRenderingContext, scale: f32) {
   let canvas = gl
     .canvas()
     .unwrap()
     .dyn_into::<HtmlCanvasElement>()
     .unwrap();
-  canvas.set_height(canvas.client_height() as u32);
-  canvas.set_width(canvas.client_width() as u32);
-  gl.viewport(0, 0, canvas.client_width(), canvas.client_height());
-}
-
-struct Mesh<'a> {
-  data: &'a mut [f32],
+  let width = ((canvas.client_width() as f32 * scale).round() as u32).max(1);
+  let height = ((canvas.client_height() as f32 * scale).round() as u32).max(1);
+  canvas.set_width(width);
+  canvas.set_height(height);
+  gl.viewport(0, 0, width as i32, height as i32);
 }
 
-impl<'a> Mesh<'a> {
-  fn transform(&mut self, matrix: &Mat4) {
-    let mut temp = [0.0_f32; 3];
-
-    for i in 0..self.data.len() / 3 {
-      // safe because the types guarantee the array size is a multiple of 3;
-      let slice: &mut [f32] = self.data[3 * i..3 * (i + 1)].borrow_mut();
-      vec3::transform_mat4(&mut temp, &slice.try_into().unwrap(), matrix);
-      slice.copy_from_slice(temp.as_slice());
+/// Redirect drawing into an offscreen texture the size of the drawing buffer.
+fn bind_render_target(gl: &WebGlRenderingContext, pipeline: &mut Pipeline) -> Result<()> {
+  let width = gl.drawing_buffer_width();
+  let height = gl.drawing_buffer_height();
+  let stale = pipeline
+    .target
+    .as_ref()
+    .is_some_and(|t| t.width != width || t.height != height);
+  if stale {
+    if let Some(target) = pipeline.target.take() {
+      gl.delete_framebuffer(Some(&target.framebuffer));
+      gl.delete_texture(Some(&target.texture));
+      gl.delete_renderbuffer(Some(&target.depth));
     }
   }
+  if pipeline.target.is_none() {
+    pipeline.target = Some(create_render_target(gl, width, height)?);
+  }
+  let target = pipeline.target.as_ref().unwrap();
+  gl.bind_framebuffer(
+    WebGlRenderingContext::FRAMEBUFFER,
+    Some(&target.framebuffer),
+  );
+  Ok(())
 }
 
-fn console_log<T: std::fmt::Debug>(obj: T) {
-  console::log_1(&JsValue::from(format!("{:#?}", obj)));
+fn create_render_target(
+  gl: &WebGlRenderingContext,
+  width: i32,
+  height: i32,
+) -> Result<RenderTarget> {
+  let texture = gl.create_texture().ok_or("Unable to create texture")?;
+  gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+  gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+    WebGlRenderingContext::TEXTURE_2D,
+    0,
+    WebGlRenderingContext::RGBA as i32,
+    width,
+    height,
+    0,
+    WebGlRenderingContext::RGBA,
+    WebGlRenderingContext::UNSIGNED_BYTE,
+    None,
+  )?;
+  // non power of 2 textures can't be mipmapped or repeated in webgl 1
+  for (param, value) in [
+    (
+      WebGlRenderingContext::TEXTURE_MIN_FILTER,
+      WebGlRenderingContext::LINEAR,
+    ),
+    (
+      WebGlRenderingContext::TEXTURE_MAG_FILTER,
+      WebGlRenderingContext::LINEAR,
+    ),
+    (
+      WebGlRenderingContext::TEXTURE_WRAP_S,
+      WebGlRenderingContext::CLAMP_TO_EDGE,
+    ),
+    (
+      WebGlRenderingContext::TEXTURE_WRAP_T,
+      WebGlRenderingContext::CLAMP_TO_EDGE,
+    ),
+  ] {
+    gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, param, value as i32);
+  }
+
+  let depth = gl
+    .create_renderbuffer()
+    .ok_or("Unable to create renderbuffer")?;
+  gl.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(&depth));
+  gl.renderbuffer_storage(
+    WebGlRenderingContext::RENDERBUFFER,
+    WebGlRenderingContext::DEPTH_COMPONENT16,
+    width,
+    height,
+  );
+
+  let framebuffer = gl
+    .create_framebuffer()
+    .ok_or("Unable to create framebuffer")?;
+  gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+  gl.framebuffer_texture_2d(
+    WebGlRenderingContext::FRAMEBUFFER,
+    WebGlRenderingContext::COLOR_ATTACHMENT0,
+    WebGlRenderingContext::TEXTURE_2D,
+    Some(&texture),
+    0,
+  );
+  gl.framebuffer_renderbuffer(
+    WebGlRenderingContext::FRAMEBUFFER,
+    WebGlRenderingContext::DEPTH_ATTACHMENT,
+    WebGlRenderingContext::RENDERBUFFER,
+    Some(&depth),
+  );
+
+  Ok(RenderTarget {
+    framebuffer,
+    texture,
+    depth,
+    width,
+    height,
+  })
 }
 
-/*
-struct Mesh<const N: usize>
-where
-  [f32; 3 * N]: Sized,
-{
-  data: [f32; 3 * N],
+/// Draw the offscreen frame to the screen, smoothing its edges.
+fn apply_fxaa(gl: &WebGlRenderingContext, pipeline: &Pipeline) {
+  let Some(target) = pipeline.target.as_ref() else {
+    return;
+  };
+  gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+  gl.use_program(Some(&pipeline.fxaa));
+  gl.disable(WebGlRenderingContext::DEPTH_TEST);
+
+  gl.active_texture(WebGlRenderingContext::TEXTURE0);
+  gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&target.texture));
+  gl.uniform1i(gl.get_uniform_location(&pipeline.fxaa, "frame").as_ref(), 0);
+  gl.uniform2f(
+    gl.get_uniform_location(&pipeline.fxaa, "resolution")
+      .as_ref(),
+    target.width as f32,
+    target.height as f32,
+  );
+
+  gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&pipeline.quad));
+  bind_attribute(gl, &pipeline.fxaa, "quadPosition", 2);
+  gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
 }
 
-impl<const N: usize> Mesh<N>
-where
-  [f32; 3 * N]: Sized,
-{
-  fn transform(&mut self, matrix: &Mat4) {
-    let mut temp = [0.0_f32; 3];
+/// Fill the screen behind the puzzle.
+fn draw_background(gl: &WebGlRenderingContext, pipeline: &Pipeline, scene: &Scene) {
+  let program = &pipeline.background;
+  gl.use_program(Some(program));
+  gl.disable(WebGlRenderingContext::DEPTH_TEST);
+  gl.depth_mask(false);
+
+  let (mode, top, bottom) = match scene.background {
+    Background::Solid(color) => (0.0, color, color),
+    Background::Gradient { top, bottom } => (0.0, top, bottom),
+    Background::Environment => (1.0, Color::WHITE, Color::WHITE),
+  };
+  let uniform = |name| gl.get_uniform_location(program, name);
+  gl.uniform1f(uniform("mode").as_ref(), mode);
+  gl.uniform1f(
+    uniform("shadow").as_ref(),
+    if scene.ground_shadow { 1.0 } else { 0.0 },
+  );
+  gl.uniform3f(
+    uniform("topColor").as_ref(),
+    top.red(),
+    top.green(),
+    top.blue(),
+  );
+  gl.uniform3f(
+    uniform("bottomColor").as_ref(),
+    bottom.red(),
+    bottom.green(),
+    bottom.blue(),
+  );
+
+  PROJECTION.with(|projection| {
+    let mut inverse = mat4::create();
+    mat4::invert(&mut inverse, projection);
+    gl.uniform_matrix4fv_with_f32_array(uniform("inverseProjection").as_ref(), false, &inverse);
+
+    let mut transform = mat4::create();
+    mat4::multiply(&mut transform, projection, &pipeline.camera);
+    mat4::invert(&mut inverse, &transform);
+    gl.uniform_matrix4fv_with_f32_array(uniform("inverseTransform").as_ref(), false, &inverse);
+  });
 
-    for i in 0..N {
-      // safe because the types guarantee the array size is a multiple of 3;
-      let slice: &mut [f32] = self.data[3 * i..3 * (i + 1)].borrow_mut();
-      vec3::transform_mat4(&mut temp, &slice.try_into().unwrap(), matrix);
-      slice.copy_from_slice(temp.as_slice());
-    }
+  gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&pipeline.quad));
+  bind_attribute(gl, program, "quadPosition", 2);
+  gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, 3);
+
+  gl.depth_mask(true);
+  gl.enable(WebGlRenderingContext::DEPTH_TEST);
+}
+
+/// The scene's attributes share slots with the full screen passes',
+/// so they are pointed back at the scene's buffers before each draw.
+fn bind_scene_attributes(gl: &WebGlRenderingContext, pipeline: &Pipeline) {
+  for (buffer, name, num_components) in [
+    (&pipeline.positions, "vertexPosition", 3),
+    (&pipeline.colors, "vertexColor", 4),
+    (&pipeline.uvs, "vertexUv", 2),
+    (&pipeline.patterns, "vertexPattern", 1),
+  ] {
+    gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
+    bind_attribute(gl, &pipeline.scene, name, num_components);
   }
 }
-*/
+
+#[allow(dead_code)]
+fn console_log<T: std::fmt::Debug>(obj: T) {
+  console::log_1(&JsValue::from(format!("{:#?}", obj)));
+}
@@ -0,0 +1,48 @@
+//! Entry point of `public/solver-worker.js`, which runs the solver off the main thread
+//! so building pruning tables and searching never hold up `render`.
+use puzzle_core::notation;
+use puzzle_core::puzzle::Puzzle;
+use puzzle_core::solver::{Search, Solver};
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+  static SOLVER: Solver = Solver::new();
+}
+
+type Result<T, E = JsValue> = std::result::Result<T, E>;
+
+/// Build the pruning tables ahead of the first request.
+#[wasm_bindgen]
+pub fn prepare() {
+  SOLVER.with(|_| ());
+}
+
+/// One solve request, advanced a depth at a time so the worker can read
+/// cancel messages in between.
+#[wasm_bindgen]
+pub struct Job {
+  search: Search,
+}
+
+#[wasm_bindgen]
+impl Job {
+  /// `state` comes from `puzzle_state` on the main thread.
+  #[wasm_bindgen(constructor)]
+  pub fn new(state: &[u8]) -> Result<Job> {
+    let puzzle = Puzzle::from_bytes(state)?;
+    let search = SOLVER.with(|solver| solver.start(&puzzle))?;
+    Ok(Job { search })
+  }
+
+  /// Length of the sequences the next step tries.
+  pub fn depth(&self) -> u8 {
+    self.search.depth()
+  }
+
+  /// Try every sequence of the current depth.
+  /// Returns the solution in move notation once one is found.
+  pub fn step(&mut self) -> Option<String> {
+    let solution = SOLVER.with(|solver| self.search.step(solver))?;
+    Some(notation::format(&solution))
+  }
+}